@@ -1,3 +1,66 @@
-pub fn main()  {
-    
-}
\ No newline at end of file
+use naive_evm::{
+    interpreter::EVM,
+    journal::JournaledState,
+    state::{Account, InMemoryDb, StateDb},
+    types::Transaction,
+};
+use primitive_types::U256;
+
+// solc output for solidity/ch01.sol: constructor followed by the runtime code
+const CH01_BIN: &str = include_str!("../solidity/outputDirectory/ch01.bin");
+
+pub fn main() {
+    let init_code = hex::decode(CH01_BIN.trim()).unwrap();
+    let mut account_db = InMemoryDb::new();
+    // the sender buys the gas of both transactions
+    account_db.insert_account(
+        Transaction::default().caller,
+        Account {
+            balance: 1_000_000,
+            ..Account::default()
+        },
+    );
+    let mut state = JournaledState::new(account_db);
+
+    // the constructor returns the runtime code
+    let deploy = Transaction {
+        gas_limit: 100000,
+        ..Transaction::default()
+    };
+    let runtime = match EVM::init(&init_code, deploy, false, &mut state).transact() {
+        Ok(result) if result.success => result.return_data,
+        Ok(result) => match result.halt_reason {
+            Some(err) => panic!("constructor halted: {}", err),
+            None => panic!("constructor reverted"),
+        },
+        Err(reason) => panic!("deployment rejected: {}", reason),
+    };
+    println!("runtime code = {}", hex::encode(&runtime));
+
+    // add(uint256,uint256) with a = 1, b = 2
+    let mut calldata = hex::decode("771602f7").unwrap();
+    for arg in [1u64, 2] {
+        let mut word = [0u8; 32];
+        U256::from(arg).to_big_endian(&mut word);
+        calldata.extend_from_slice(&word);
+    }
+
+    let txn = Transaction {
+        nonce: 1,
+        data: calldata,
+        value: 0,
+        gas_limit: 100000,
+        ..Transaction::default()
+    };
+    let mut evm = EVM::init(&runtime, txn, false, &mut state);
+    match evm.transact() {
+        Ok(result) if result.success => {
+            println!("add(1, 2) = {}", U256::from(&result.return_data[..]));
+        }
+        Ok(result) => match result.halt_reason {
+            Some(err) => println!("halted: {}", err),
+            None => println!("reverted: {}", hex::encode(result.return_data)),
+        },
+        Err(reason) => println!("rejected: {}", reason),
+    }
+}
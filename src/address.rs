@@ -0,0 +1,103 @@
+//! Address derivation for contracts deployed by CREATE and CREATE2.
+
+use primitive_types::U256;
+use sha3::{Digest, Keccak256};
+
+use crate::types::TransparentU256;
+
+/// `keccak256(rlp([sender, nonce]))[12..]`
+pub fn create_address(sender: &U256, nonce: u64) -> TransparentU256 {
+    let sender = address_bytes(sender);
+    let nonce_bytes = nonce.to_be_bytes();
+    let nonce_bytes = &nonce_bytes[nonce.leading_zeros() as usize / 8..];
+
+    // rlp: a single byte below 0x80 is its own encoding, zero is the empty string
+    let mut nonce_rlp = Vec::with_capacity(9);
+    match nonce_bytes {
+        [] => nonce_rlp.push(0x80),
+        [byte] if *byte < 0x80 => nonce_rlp.push(*byte),
+        bytes => {
+            nonce_rlp.push(0x80 + bytes.len() as u8);
+            nonce_rlp.extend_from_slice(bytes);
+        }
+    }
+
+    let mut stream = Vec::with_capacity(32);
+    stream.push(0xc0 + (1 + sender.len() + nonce_rlp.len()) as u8);
+    stream.push(0x80 + sender.len() as u8);
+    stream.extend_from_slice(&sender);
+    stream.extend_from_slice(&nonce_rlp);
+    address_from_hash(&stream)
+}
+
+/// `keccak256(0xff ++ sender ++ salt ++ keccak256(init_code))[12..]`
+pub fn create2_address(sender: &U256, salt: &U256, init_code: &[u8]) -> TransparentU256 {
+    let mut salt_bytes = [0u8; 32];
+    salt.to_big_endian(&mut salt_bytes);
+
+    let mut stream = Vec::with_capacity(85);
+    stream.push(0xff);
+    stream.extend_from_slice(&address_bytes(sender));
+    stream.extend_from_slice(&salt_bytes);
+    stream.extend_from_slice(&Keccak256::digest(init_code));
+    address_from_hash(&stream)
+}
+
+fn address_bytes(address: &U256) -> [u8; 20] {
+    let mut word = [0u8; 32];
+    address.to_big_endian(&mut word);
+    let mut bytes = [0u8; 20];
+    bytes.copy_from_slice(&word[12..]);
+    bytes
+}
+
+fn address_from_hash(data: &[u8]) -> TransparentU256 {
+    let hash = Keccak256::digest(data);
+    U256::from(&hash[12..]).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn address(hex: &str) -> TransparentU256 {
+        U256::from_str_radix(hex, 16).unwrap().into()
+    }
+
+    #[test]
+    fn create_addresses_follow_the_sender_nonce() {
+        let sender = address("6ac7ea33f8831ea9dcc53393aaa88b25a785dbf0");
+        let expected = [
+            "cd234a471b72ba2f1ccf0a70fcaba648a5eecd8d",
+            "343c43a37d37dff08ae8c4a11544c718abb4fcf8",
+            "f778b86fa74e846c4f0a1fbd1335fe81c00a0c91",
+            "fffd933a0bc612844eaf0c6fe3e5b8e9b6c1d19c",
+        ];
+        for (nonce, expected) in expected.iter().enumerate() {
+            assert_eq!(create_address(&sender, nonce as u64), address(expected));
+        }
+    }
+
+    #[test]
+    fn create2_matches_the_eip_1014_examples() {
+        let zero = U256::zero();
+        assert_eq!(
+            create2_address(&zero, &zero, &[0x00]),
+            address("4d1a2e2bb4f88f0250f26ffff098b0b30b26bf38")
+        );
+        let sender = address("deadbeef00000000000000000000000000000000");
+        assert_eq!(
+            create2_address(&sender, &zero, &[0x00]),
+            address("b928f69bb1d91cd65274e3c79d8986362984fda3")
+        );
+        let sender = address("00000000000000000000000000000000deadbeef");
+        assert_eq!(
+            create2_address(&sender, &0xcafebabe_u64.into(), &[0xde, 0xad, 0xbe, 0xef]),
+            address("60f3f640a8508fc6a86d45df051962668e1e8ac7")
+        );
+        assert_eq!(
+            create2_address(&zero, &zero, &[]),
+            address("e33c0c7f7df4809055c3eba6c09cfe4baf1bd9e0")
+        );
+    }
+}
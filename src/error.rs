@@ -0,0 +1,77 @@
+use primitive_types::U256;
+use thiserror::Error;
+
+/// Reasons for an exceptional halt. Any of these reverts the frame that hit
+/// it and consumes all of its remaining gas. A transaction that fails
+/// validation is rejected before anything runs.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum EvmError {
+    #[error("stack underflow")]
+    StackUnderflow,
+    #[error("stack overflow")]
+    StackOverflow,
+    #[error("invalid jump destination")]
+    InvalidJump,
+    #[error("out of gas")]
+    OutOfGas,
+    #[error("invalid opcode 0x{0:02x}")]
+    InvalidOpcode(u8),
+    #[error("state changing operation during static call")]
+    StaticStateChange,
+    #[error("memory limit exceeded")]
+    MemoryLimit,
+    #[error("return data out of bounds")]
+    ReturnDataOutOfBounds,
+    #[error("init code of {0} bytes exceeds the limit")]
+    InitcodeTooLarge(usize),
+    #[error("deployed code of {0} bytes exceeds the limit")]
+    CodeTooLarge(usize),
+    #[error("deployed code starts with 0xEF")]
+    InvalidCodePrefix,
+}
+
+/// Why a transaction cannot be included in a block.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum InvalidTransaction {
+    #[error("nonce {got} does not match the sender's nonce {expected}")]
+    NonceMismatch { expected: u64, got: u64 },
+    #[error("sender nonce is at its maximum")]
+    NonceOverflow,
+    #[error("sender balance {balance} does not cover {cost}")]
+    InsufficientFunds { balance: u64, cost: u64 },
+    #[error("gas limit {gas_limit} is below the intrinsic gas {intrinsic_gas}")]
+    IntrinsicGasTooLow { gas_limit: u64, intrinsic_gas: u64 },
+    #[error("gas limit {gas_limit} exceeds the block gas limit {block_gas_limit}")]
+    GasLimitExceedsBlock {
+        gas_limit: u64,
+        block_gas_limit: u64,
+    },
+    #[error("gas price {gas_price} is below the base fee {basefee}")]
+    GasPriceBelowBaseFee { gas_price: u64, basefee: u64 },
+    #[error("max fee per blob gas {max_fee} is below the blob base fee {blob_basefee}")]
+    BlobFeeBelowBaseFee { max_fee: u64, blob_basefee: U256 },
+    #[error("blob versioned hash {0:#x} does not have the KZG version")]
+    InvalidBlobVersionedHash(U256),
+    #[error("init code of {0} bytes exceeds the limit")]
+    InitcodeTooLarge(usize),
+}
+
+/// Why a precompile failed. Like an exceptional halt, this fails the call
+/// and consumes all the gas it was given.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PrecompileError {
+    #[error("out of gas")]
+    OutOfGas,
+    #[error("input has the wrong length")]
+    InvalidInputLength,
+    #[error("point is not on the curve")]
+    InvalidPoint,
+    #[error("final block flag is neither 0 nor 1")]
+    InvalidFinalFlag,
+    #[error("commitment does not match the versioned hash")]
+    InvalidVersionedHash,
+    #[error("KZG proof does not verify")]
+    InvalidProof,
+}
+
+pub type EvmResult<T> = Result<T, EvmError>;
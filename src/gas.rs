@@ -0,0 +1,480 @@
+use std::collections::HashMap;
+
+use once_cell::sync::Lazy;
+use primitive_types::{U256, U512};
+
+use crate::{op_code::*, spec::SpecId, types::AccessListItem};
+
+// fee tiers from the yellow paper
+pub const ZERO: u64 = 0;
+pub const BASE: u64 = 2;
+pub const VERYLOW: u64 = 3;
+pub const LOW: u64 = 5;
+pub const MID: u64 = 8;
+pub const HIGH: u64 = 10;
+
+pub const JUMPDEST_COST: u64 = 1;
+pub const EXP_COST: u64 = 10;
+pub const EXP_BYTE: u64 = 50;
+// EIP-160 raised the per-byte price in Spurious Dragon
+pub const EXP_BYTE_FRONTIER: u64 = 10;
+pub const KECCAK256_COST: u64 = 30;
+pub const KECCAK256_WORD: u64 = 6;
+pub const COPY_WORD: u64 = 3;
+pub const MEMORY_WORD: u64 = 3;
+pub const BLOCKHASH_COST: u64 = 20;
+pub const LOG_COST: u64 = 375;
+pub const LOG_TOPIC: u64 = 375;
+pub const LOG_DATA: u64 = 8;
+pub const CREATE_COST: u64 = 32000;
+pub const CODE_DEPOSIT: u64 = 200;
+// EIP-3860 charge per word of init code
+pub const INITCODE_WORD: u64 = 2;
+pub const SELFDESTRUCT_COST: u64 = 5000;
+// refunded for every destructed account until London dropped it
+pub const SELFDESTRUCT_REFUND: i64 = 24000;
+
+// EIP-2929: accessing an account or slot that is already warm
+pub const WARM_STORAGE_READ: u64 = 100;
+pub const COLD_ACCOUNT_ACCESS: u64 = 2600;
+pub const COLD_SLOAD: u64 = 2100;
+pub const SSTORE_SET: u64 = 20000;
+// SSTORE_RESET minus the cold SLOAD surcharge, as priced since Berlin
+pub const SSTORE_RESET: u64 = 5000 - COLD_SLOAD;
+// EIP-2200: SSTORE fails if no more than the call stipend is left
+pub const SSTORE_SENTRY: u64 = 2300;
+// EIP-3529 refund for clearing a slot, and the cap on refunds as a
+// fraction of the gas used
+pub const SSTORE_CLEARS_SCHEDULE: i64 = 4800;
+pub const MAX_REFUND_QUOTIENT: u64 = 5;
+// the same before London, and the price of a reset before Berlin
+pub const SSTORE_CLEARS_FRONTIER: i64 = 15000;
+pub const MAX_REFUND_QUOTIENT_FRONTIER: u64 = 2;
+pub const SSTORE_RESET_FRONTIER: u64 = 5000;
+// price of a no-op SSTORE under EIP-1283 and EIP-2200
+pub const SSTORE_NOOP_CONSTANTINOPLE: u64 = 200;
+pub const SSTORE_NOOP_ISTANBUL: u64 = 800;
+
+pub const TX_BASE: u64 = 21000;
+pub const TX_CREATE: u64 = 32000;
+pub const TX_DATA_ZERO: u64 = 4;
+pub const TX_DATA_NON_ZERO: u64 = 16;
+// EIP-2028 cut the price of a non-zero byte in Istanbul
+pub const TX_DATA_NON_ZERO_FRONTIER: u64 = 68;
+// EIP-7623 minimum a transaction pays per token of calldata
+pub const TX_FLOOR_PER_TOKEN: u64 = 10;
+// EIP-2930 price of declaring an address or a slot up front
+pub const ACCESS_LIST_ADDRESS: u64 = 2400;
+pub const ACCESS_LIST_STORAGE_KEY: u64 = 1900;
+
+// EIP-4844 blob fee market
+pub const BLOBHASH_COST: u64 = 3;
+pub const GAS_PER_BLOB: u64 = 1 << 17;
+pub const MIN_BLOB_BASE_FEE: u64 = 1;
+pub const BLOB_BASE_FEE_UPDATE_FRACTION: u64 = 3338477;
+// EIP-7691 raised the blob target in Prague and slowed the price updates
+pub const BLOB_BASE_FEE_UPDATE_FRACTION_PRAGUE: u64 = 5007716;
+
+pub const CALL_VALUE: u64 = 9000;
+// given to the callee for free when value is sent along
+pub const CALL_STIPEND: u64 = 2300;
+pub const NEW_ACCOUNT: u64 = 25000;
+
+/// Static part of every opcode's cost. Anything depending on operands,
+/// memory or state is charged by the opcode itself on top of this.
+pub static GASCOST: Lazy<HashMap<u8, u64>> = Lazy::new(|| {
+    let mut gas_costs = HashMap::new();
+    for op in [STOP, RETURN, REVERT] {
+        gas_costs.insert(op, ZERO);
+    }
+    for op in [
+        ADDRESS,
+        ORIGIN,
+        CALLER,
+        CALLVALUE,
+        CALLDATASIZE,
+        CODESIZE,
+        GASPRICE,
+        COINBASE,
+        TIMESTAMP,
+        NUMBER,
+        PREVRANDAO,
+        GASLIMIT,
+        CHAINID,
+        RETURNDATASIZE,
+        POP,
+        PC,
+        MSIZE,
+        GAS,
+        BASEFEE,
+        BLOBBASEFEE,
+        PUSH0,
+    ] {
+        gas_costs.insert(op, BASE);
+    }
+    for op in [
+        ADD,
+        SUB,
+        NOT,
+        LT,
+        GT,
+        SLT,
+        SGT,
+        EQ,
+        ISZERO,
+        AND,
+        OR,
+        XOR,
+        BYTE,
+        SHL,
+        SHR,
+        SAR,
+        CALLDATALOAD,
+        MLOAD,
+        MSTORE,
+        MSTORE8,
+        CALLDATACOPY,
+        CODECOPY,
+        RETURNDATACOPY,
+        MCOPY,
+    ] {
+        gas_costs.insert(op, VERYLOW);
+    }
+    for op in PUSH1..=PUSH32 {
+        gas_costs.insert(op, VERYLOW);
+    }
+    for op in DUP1..=DUP16 {
+        gas_costs.insert(op, VERYLOW);
+    }
+    for op in SWAP1..=SWAP16 {
+        gas_costs.insert(op, VERYLOW);
+    }
+    for op in [MUL, DIV, SDIV, MOD, SMOD, SIGNEXTEND, SELFBALANCE] {
+        gas_costs.insert(op, LOW);
+    }
+    for op in [ADDMOD, MULMOD, JUMP] {
+        gas_costs.insert(op, MID);
+    }
+    gas_costs.insert(JUMPI, HIGH);
+    gas_costs.insert(EXP, EXP_COST);
+    gas_costs.insert(SHA3, KECCAK256_COST);
+    gas_costs.insert(JUMPDEST, JUMPDEST_COST);
+    gas_costs.insert(BLOCKHASH, BLOCKHASH_COST);
+    gas_costs.insert(BLOBHASH, BLOBHASH_COST);
+    for op in [
+        BALANCE,
+        EXTCODESIZE,
+        EXTCODECOPY,
+        EXTCODEHASH,
+        SLOAD,
+        TLOAD,
+        TSTORE,
+        CALL,
+        CALLCODE,
+        DELEGATECALL,
+        STATICCALL,
+    ] {
+        gas_costs.insert(op, WARM_STORAGE_READ);
+    }
+    // SSTORE is priced entirely by `sstore_cost`
+    gas_costs.insert(SSTORE, ZERO);
+    for (topics, op) in [LOG0, LOG1, LOG2, LOG3, LOG4].into_iter().enumerate() {
+        gas_costs.insert(op, LOG_COST + LOG_TOPIC * topics as u64);
+    }
+    for op in [CREATE, CREATE2] {
+        gas_costs.insert(op, CREATE_COST);
+    }
+    gas_costs.insert(SELFDESTRUCT, SELFDESTRUCT_COST);
+    gas_costs
+});
+
+/// Static cost of `op` under `spec`, None if it has no fixed part.
+pub fn static_cost(op: u8, spec: SpecId) -> Option<u64> {
+    if !spec.is_enabled(SpecId::Berlin) {
+        if let Some(cost) = legacy_cost(op, spec) {
+            return Some(cost);
+        }
+    }
+    GASCOST.get(&op).copied()
+}
+
+/// Flat prices of the state-accessing opcodes before EIP-2929 made them
+/// depend on warm and cold access. Tangerine Whistle (EIP-150) and
+/// Istanbul (EIP-1884) raised them to match their real cost.
+fn legacy_cost(op: u8, spec: SpecId) -> Option<u64> {
+    let tangerine = spec.is_enabled(SpecId::TangerineWhistle);
+    let istanbul = spec.is_enabled(SpecId::Istanbul);
+    let cost = match op {
+        BALANCE if istanbul => 700,
+        BALANCE if tangerine => 400,
+        BALANCE => 20,
+        EXTCODESIZE | EXTCODECOPY if tangerine => 700,
+        EXTCODESIZE | EXTCODECOPY => 20,
+        EXTCODEHASH if istanbul => 700,
+        EXTCODEHASH => 400,
+        SLOAD if istanbul => SSTORE_NOOP_ISTANBUL,
+        SLOAD if tangerine => 200,
+        SLOAD => 50,
+        CALL | CALLCODE | DELEGATECALL | STATICCALL if tangerine => 700,
+        CALL | CALLCODE | DELEGATECALL | STATICCALL => 40,
+        SELFDESTRUCT if tangerine => SELFDESTRUCT_COST,
+        SELFDESTRUCT => ZERO,
+        _ => return None,
+    };
+    Some(cost)
+}
+
+/// Number of 32-byte words needed to hold `len` bytes.
+pub fn num_words(len: usize) -> u64 {
+    (len as u64).div_ceil(32)
+}
+
+/// Total cost of a memory of `words` words. The quadratic term makes large
+/// memories prohibitively expensive.
+pub fn memory_cost(words: u64) -> u64 {
+    MEMORY_WORD * words + words * words / 512
+}
+
+/// Cost of growing memory from `current_words` to `new_words`.
+pub fn memory_expansion_cost(current_words: u64, new_words: u64) -> u64 {
+    if new_words <= current_words {
+        return 0;
+    }
+    memory_cost(new_words) - memory_cost(current_words)
+}
+
+/// EXP charges for every byte of the exponent.
+pub fn exp_cost(spec: SpecId, exponent: &U256) -> u64 {
+    let bytes = (exponent.bits() as u64).div_ceil(8);
+    if spec.is_enabled(SpecId::SpuriousDragon) {
+        EXP_BYTE * bytes
+    } else {
+        EXP_BYTE_FRONTIER * bytes
+    }
+}
+
+pub fn keccak256_cost(len: usize) -> u64 {
+    KECCAK256_WORD * num_words(len)
+}
+
+pub fn copy_cost(len: usize) -> u64 {
+    COPY_WORD * num_words(len)
+}
+
+pub fn log_data_cost(len: usize) -> u64 {
+    LOG_DATA * len as u64
+}
+
+pub fn code_deposit_cost(len: usize) -> u64 {
+    CODE_DEPOSIT * len as u64
+}
+
+pub fn initcode_cost(len: usize) -> u64 {
+    INITCODE_WORD * num_words(len)
+}
+
+/// Whether SSTORE is priced against the slot's value at the start of the
+/// transaction (EIP-1283, reverted in Petersburg, then EIP-2200).
+pub fn is_sstore_net_metered(spec: SpecId) -> bool {
+    spec == SpecId::Constantinople || spec.is_enabled(SpecId::Istanbul)
+}
+
+/// Net-metered SSTORE prices of `spec`: a no-op or dirty write, a reset of
+/// a clean slot and the refund for clearing one.
+fn sstore_schedule(spec: SpecId) -> (u64, u64, i64) {
+    let clears = if spec.is_enabled(SpecId::London) {
+        SSTORE_CLEARS_SCHEDULE
+    } else {
+        SSTORE_CLEARS_FRONTIER
+    };
+    if spec.is_enabled(SpecId::Berlin) {
+        (WARM_STORAGE_READ, SSTORE_RESET, clears)
+    } else if spec.is_enabled(SpecId::Istanbul) {
+        (SSTORE_NOOP_ISTANBUL, SSTORE_RESET_FRONTIER, clears)
+    } else {
+        (SSTORE_NOOP_CONSTANTINOPLE, SSTORE_RESET_FRONTIER, clears)
+    }
+}
+
+/// SSTORE cost given the slot's value at the start of the transaction, its
+/// current value and the new one. Net metering follows EIP-2200, priced as
+/// of `spec`; older forks only look at the current value.
+pub fn sstore_cost(spec: SpecId, original: &U256, current: &U256, new: &U256) -> u64 {
+    if !is_sstore_net_metered(spec) {
+        return if current.is_zero() && !new.is_zero() {
+            SSTORE_SET
+        } else {
+            SSTORE_RESET_FRONTIER
+        };
+    }
+    let (noop, reset, _) = sstore_schedule(spec);
+    if current == new || original != current {
+        // no-op, or the slot is already dirty in this transaction
+        noop
+    } else if original.is_zero() {
+        SSTORE_SET
+    } else {
+        reset
+    }
+}
+
+/// Refund counter change for the same SSTORE. Negative when an earlier
+/// refund has to be taken back.
+pub fn sstore_refund(spec: SpecId, original: &U256, current: &U256, new: &U256) -> i64 {
+    let (noop, reset, clears) = sstore_schedule(spec);
+    if !is_sstore_net_metered(spec) {
+        return if !current.is_zero() && new.is_zero() {
+            clears
+        } else {
+            0
+        };
+    }
+    if current == new {
+        return 0;
+    }
+    let mut refund = 0;
+    if original == current {
+        if new.is_zero() {
+            refund += clears;
+        }
+        return refund;
+    }
+    if !original.is_zero() {
+        if current.is_zero() {
+            refund -= clears;
+        } else if new.is_zero() {
+            refund += clears;
+        }
+    }
+    if original == new {
+        // the slot goes back to its original value
+        refund += if original.is_zero() {
+            (SSTORE_SET - noop) as i64
+        } else {
+            (reset - noop) as i64
+        };
+    }
+    refund
+}
+
+/// Most of the gas used that refunds may pay back, a half before London
+/// and a fifth since EIP-3529.
+pub fn max_refund(spec: SpecId, gas_used: u64) -> u64 {
+    if spec.is_enabled(SpecId::London) {
+        gas_used / MAX_REFUND_QUOTIENT
+    } else {
+        gas_used / MAX_REFUND_QUOTIENT_FRONTIER
+    }
+}
+
+/// Extra cost of a CALL for moving value, and for bringing a new account
+/// into existence.
+pub fn call_value_cost(transfers_value: bool, creates_account: bool) -> u64 {
+    let mut cost = 0;
+    if transfers_value {
+        cost += CALL_VALUE;
+    }
+    if creates_account {
+        cost += NEW_ACCOUNT;
+    }
+    cost
+}
+
+/// Gas a transaction pays before its first instruction runs. `payload` is
+/// the calldata, or the init code for a contract creation.
+pub fn intrinsic_gas(
+    spec: SpecId,
+    payload: &[u8],
+    is_create: bool,
+    access_list: &[AccessListItem],
+) -> u64 {
+    let zeros = payload.iter().filter(|b| **b == 0).count() as u64;
+    let non_zeros = payload.len() as u64 - zeros;
+    let non_zero_cost = if spec.is_enabled(SpecId::Istanbul) {
+        TX_DATA_NON_ZERO
+    } else {
+        TX_DATA_NON_ZERO_FRONTIER
+    };
+    let mut gas = TX_BASE + TX_DATA_ZERO * zeros + non_zero_cost * non_zeros;
+    // creation transactions cost extra since Homestead (EIP-2)
+    if is_create && spec.is_enabled(SpecId::Homestead) {
+        gas += TX_CREATE;
+    }
+    if is_create && spec.is_enabled(SpecId::Shanghai) {
+        gas += initcode_cost(payload.len());
+    }
+    for item in access_list {
+        gas += ACCESS_LIST_ADDRESS + ACCESS_LIST_STORAGE_KEY * item.storage_keys.len() as u64;
+    }
+    gas
+}
+
+/// Least a transaction pays in Prague however little it runs, by EIP-7623.
+/// A non-zero byte of `payload` counts as four tokens, a zero byte as one.
+pub fn floor_gas(payload: &[u8]) -> u64 {
+    let zeros = payload.iter().filter(|b| **b == 0).count() as u64;
+    let tokens = zeros + 4 * (payload.len() as u64 - zeros);
+    TX_BASE + TX_FLOOR_PER_TOKEN * tokens
+}
+
+/// Approximates `factor * e ** (numerator / denominator)` with integers, as
+/// EIP-4844 specifies for the blob base fee. A result too large for a
+/// `U256` is clamped to `U256::MAX`.
+pub fn fake_exponential(factor: u64, numerator: u64, denominator: u64) -> U256 {
+    let (numerator, denominator) = (U512::from(numerator), U512::from(denominator));
+    // the series is summed times the denominator, so anything from here on
+    // divides down to more than U256::MAX
+    let limit = (U512::from(U256::MAX) + 1) * denominator;
+    let mut output = U512::zero();
+    let mut accum = U512::from(factor) * denominator;
+    let mut i = U512::one();
+    while !accum.is_zero() {
+        output += accum;
+        if output >= limit {
+            return U256::MAX;
+        }
+        accum = accum * numerator / (denominator * i);
+        i += U512::one();
+    }
+    U256::try_from(output / denominator).expect("output is below the limit")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fake_exponential_saturates() {
+        assert_eq!(fake_exponential(1, 1_000_000_000, 3338477), U256::MAX);
+        assert_eq!(fake_exponential(u64::MAX, u64::MAX, 1), U256::MAX);
+    }
+
+    #[test]
+    fn fake_exponential_matches_the_eip_4844_vectors() {
+        let vectors: [(u64, u64, u64, u64); 16] = [
+            (1, 0, 1, 1),
+            (38493, 0, 1000, 38493),
+            (0, 1234, 2345, 0),
+            (1, 2, 1, 6),
+            (1, 4, 2, 6),
+            (1, 3, 1, 16),
+            (1, 6, 2, 18),
+            (1, 4, 1, 49),
+            (1, 8, 2, 50),
+            (10, 8, 2, 542),
+            (11, 8, 2, 596),
+            (1, 5, 1, 136),
+            (1, 5, 2, 11),
+            (2, 5, 2, 23),
+            (1, 50000000, 2225652, 5709098764),
+            (1, 380928, 3338477, 1),
+        ];
+        for (factor, numerator, denominator, expected) in vectors {
+            assert_eq!(
+                fake_exponential(factor, numerator, denominator),
+                U256::from(expected),
+                "fake_exponential({factor}, {numerator}, {denominator})"
+            );
+        }
+    }
+}
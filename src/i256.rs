@@ -0,0 +1,164 @@
+//! Two's-complement view of a `U256`, used by the signed opcodes.
+
+use std::cmp::Ordering;
+
+use primitive_types::U256;
+
+pub fn is_negative(value: &U256) -> bool {
+    value.bit(255)
+}
+
+pub fn two_compl(value: U256) -> U256 {
+    (!value).overflowing_add(U256::one()).0
+}
+
+/// Magnitude of a signed value. `-2^255` has no positive counterpart and
+/// maps to itself, which is what the division and modulo rules rely on.
+pub fn abs(value: U256) -> U256 {
+    if is_negative(&value) {
+        two_compl(value)
+    } else {
+        value
+    }
+}
+
+pub fn i256_cmp(a: &U256, b: &U256) -> Ordering {
+    match (is_negative(a), is_negative(b)) {
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        // same sign, two's complement keeps the unsigned order
+        _ => a.cmp(b),
+    }
+}
+
+/// Signed division truncating towards zero. `x / 0 = 0` and
+/// `-2^255 / -1 = -2^255`.
+pub fn i256_div(a: U256, b: U256) -> U256 {
+    if b.is_zero() {
+        return U256::zero();
+    }
+    let quotient = abs(a) / abs(b);
+    if is_negative(&a) != is_negative(&b) {
+        two_compl(quotient)
+    } else {
+        quotient
+    }
+}
+
+/// Signed remainder, taking the sign of the dividend. `x % 0 = 0`.
+pub fn i256_mod(a: U256, b: U256) -> U256 {
+    if b.is_zero() {
+        return U256::zero();
+    }
+    let remainder = abs(a) % abs(b);
+    if is_negative(&a) {
+        two_compl(remainder)
+    } else {
+        remainder
+    }
+}
+
+/// Arithmetic right shift, filling with the sign bit.
+pub fn i256_sar(value: U256, shift: U256) -> U256 {
+    let negative = is_negative(&value);
+    if shift >= U256::from(256) {
+        return if negative { U256::MAX } else { U256::zero() };
+    }
+    let shift = shift.as_usize();
+    if negative {
+        !((!value) >> shift)
+    } else {
+        value >> shift
+    }
+}
+
+/// Extends the sign of the `byte_index`-th lowest byte of `value` to the
+/// full 256 bits.
+pub fn sign_extend(byte_index: U256, value: U256) -> U256 {
+    if byte_index >= U256::from(31) {
+        return value;
+    }
+    let bit = byte_index.as_usize() * 8 + 7;
+    let mask = (U256::one() << bit) - 1;
+    if value.bit(bit) {
+        value | !mask
+    } else {
+        value & mask
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn neg(value: u64) -> U256 {
+        two_compl(U256::from(value))
+    }
+
+    fn min() -> U256 {
+        U256::one() << 255
+    }
+
+    #[test]
+    fn div_truncates_towards_zero() {
+        assert_eq!(i256_div(neg(7), U256::from(2)), neg(3));
+        assert_eq!(i256_div(U256::from(7), neg(2)), neg(3));
+        assert_eq!(i256_div(neg(7), neg(2)), U256::from(3));
+        assert_eq!(i256_div(neg(7), U256::zero()), U256::zero());
+    }
+
+    #[test]
+    fn div_of_min_by_minus_one_overflows_to_min() {
+        assert_eq!(i256_div(min(), neg(1)), min());
+        assert_eq!(i256_div(min(), U256::one()), min());
+    }
+
+    #[test]
+    fn mod_takes_the_sign_of_the_dividend() {
+        assert_eq!(i256_mod(neg(7), U256::from(3)), neg(1));
+        assert_eq!(i256_mod(U256::from(7), neg(3)), U256::one());
+        assert_eq!(i256_mod(neg(7), neg(3)), neg(1));
+        assert_eq!(i256_mod(min(), neg(1)), U256::zero());
+        assert_eq!(i256_mod(neg(7), U256::zero()), U256::zero());
+    }
+
+    #[test]
+    fn cmp_orders_negatives_first() {
+        assert_eq!(i256_cmp(&neg(1), &U256::one()), Ordering::Less);
+        assert_eq!(i256_cmp(&min(), &neg(1)), Ordering::Less);
+        assert_eq!(i256_cmp(&U256::one(), &U256::zero()), Ordering::Greater);
+    }
+
+    #[test]
+    fn sar_fills_with_the_sign_bit() {
+        assert_eq!(i256_sar(neg(16), U256::from(2)), neg(4));
+        assert_eq!(i256_sar(neg(1), U256::from(255)), U256::MAX);
+        assert_eq!(i256_sar(min(), U256::from(255)), U256::MAX);
+        assert_eq!(i256_sar(U256::from(16), U256::from(2)), U256::from(4));
+    }
+
+    #[test]
+    fn sar_by_256_or_more_saturates() {
+        assert_eq!(i256_sar(neg(16), U256::from(256)), U256::MAX);
+        assert_eq!(i256_sar(neg(16), U256::MAX), U256::MAX);
+        assert_eq!(i256_sar(U256::MAX >> 1, U256::from(256)), U256::zero());
+    }
+
+    #[test]
+    fn sign_extend_copies_the_sign_bit_of_the_byte() {
+        assert_eq!(sign_extend(U256::zero(), U256::from(0xff)), U256::MAX);
+        assert_eq!(
+            sign_extend(U256::zero(), U256::from(0x17f)),
+            U256::from(0x7f)
+        );
+        assert_eq!(sign_extend(U256::one(), U256::from(0x8000)), neg(0x8000));
+    }
+
+    #[test]
+    fn sign_extend_from_byte_31_or_higher_is_a_no_op() {
+        let value = U256::from(0x80) << 248;
+        assert_eq!(sign_extend(U256::from(31), value), value);
+        assert_eq!(sign_extend(U256::from(31), U256::MAX), U256::MAX);
+        assert_eq!(sign_extend(U256::MAX, U256::from(0xff)), U256::from(0xff));
+    }
+}
@@ -0,0 +1,3059 @@
+use std::{
+    cmp::Ordering,
+    collections::HashSet,
+    fmt::{Display, Formatter},
+    mem,
+};
+
+use primitive_types::{U256, U512};
+use sha3::Digest;
+
+use crate::{
+    address::{create2_address, create_address},
+    error::{EvmError, EvmResult, InvalidTransaction},
+    gas,
+    i256::{i256_cmp, i256_div, i256_mod, i256_sar, sign_extend},
+    journal::{Checkpoint, JournaledState},
+    memory::Memory,
+    op_code::*,
+    precompile::{Precompiles, VERSIONED_HASH_VERSION_KZG},
+    spec::SpecId,
+    state::StateDb,
+    types::{Block, EVMLog, ExecutionResult, Transaction, TransparentU256},
+};
+
+pub struct EVM<'a, DB> {
+    code: Vec<u8>,
+    pc: usize,
+    // 在堆栈中，每个元素长度为256位 最大深度1024
+    stack: Vec<TransparentU256>,
+    memory: Memory,
+    vaild_jump_dest: HashSet<usize>,
+    current_block: Block,
+    // fork whose rules the code runs under
+    spec: SpecId,
+    // shared with every nested call frame
+    precompiles: Precompiles,
+    // world state, shared with every nested call frame
+    state: &'a mut JournaledState<DB>,
+    // frames waiting on a CALL or CREATE to finish, innermost last
+    callers: Vec<(Frame, Pending)>,
+    transaction: Transaction,
+    return_data: Vec<u8>,
+    // output of the last sub-call, read by RETURNDATASIZE and RETURNDATACOPY
+    return_data_buffer: Vec<u8>,
+    success: bool,
+    is_static: bool,
+    gas_used: u64,
+    // number of call frames above this one
+    depth: usize,
+}
+
+impl<DB> Display for EVM<'_, DB> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "Evm stack: {:?} memmory: {:?}", self.stack, self.memory)
+    }
+}
+
+impl<'a, DB: StateDb> EVM<'a, DB> {
+    pub fn init(
+        code: &[u8],
+        transaction: Transaction,
+        is_static: bool,
+        state: &'a mut JournaledState<DB>,
+    ) -> Self {
+        let frame = Frame::new(code.to_vec(), transaction, is_static, 0);
+        Self {
+            code: frame.code,
+            pc: frame.pc,
+            stack: frame.stack,
+            memory: frame.memory,
+            vaild_jump_dest: frame.vaild_jump_dest,
+            current_block: Block::default(),
+            spec: SpecId::default(),
+            precompiles: Precompiles::default(),
+            state,
+            callers: Vec::new(),
+            transaction: frame.transaction,
+            return_data: frame.return_data,
+            return_data_buffer: frame.return_data_buffer,
+            success: frame.success,
+            is_static: frame.is_static,
+            gas_used: frame.gas_used,
+            depth: frame.depth,
+        }
+    }
+
+    pub fn with_block(mut self, block: Block) -> Self {
+        self.current_block = block;
+        self
+    }
+
+    pub fn with_spec(mut self, spec: SpecId) -> Self {
+        self.spec = spec;
+        self
+    }
+
+    /// Replaces the mainnet precompiles, e.g. to add custom ones.
+    pub fn with_precompiles(mut self, precompiles: Precompiles) -> Self {
+        self.precompiles = precompiles;
+        self
+    }
+
+    pub fn spec(&self) -> SpecId {
+        self.spec
+    }
+
+    pub fn stack(&self) -> &[TransparentU256] {
+        &self.stack
+    }
+
+    pub fn memory(&self) -> &[u8] {
+        self.memory.as_slice()
+    }
+
+    pub fn logs(&self) -> &[EVMLog] {
+        self.state.logs()
+    }
+
+    pub fn return_data(&self) -> &[u8] {
+        &self.return_data
+    }
+
+    pub fn state(&self) -> &JournaledState<DB> {
+        self.state
+    }
+
+    pub fn transaction(&self) -> &Transaction {
+        &self.transaction
+    }
+
+    pub fn gas_used(&self) -> u64 {
+        self.gas_used
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.success
+    }
+
+    pub fn next_instruction(&mut self) -> u8 {
+        let instruction = self.code[self.pc];
+        self.pc += 1;
+        instruction
+    }
+
+    pub fn push(&mut self, size: usize) -> EvmResult<()> {
+        // code may end in the middle of the immediate, missing bytes read as zero
+        let mut data = [0u8; 32];
+        let end = self.code.len().min(self.pc + size);
+        data[..end - self.pc].copy_from_slice(&self.code[self.pc..end]);
+        let value = U256::from(&data[..size]);
+        self.stack.push(value.into());
+        self.pc += size;
+        Ok(())
+    }
+
+    fn gas_left(&self) -> u64 {
+        self.transaction.gas_limit.saturating_sub(self.gas_used)
+    }
+
+    /// Gas a sub-call or creation may receive at most: all of it before
+    /// EIP-150, all but one 64th after.
+    fn max_call_gas(&self) -> u64 {
+        let gas_left = self.gas_left();
+        if !self.spec.is_enabled(SpecId::TangerineWhistle) {
+            return gas_left;
+        }
+        gas_left - gas_left / 64
+    }
+
+    /// Gives back gas a sub-call did not use.
+    fn return_gas(&mut self, gas: u64) {
+        self.gas_used = self.gas_used.saturating_sub(gas);
+    }
+
+    /// Charges `cost` to this frame, failing once it runs past its limit.
+    fn use_gas(&mut self, cost: u64) -> EvmResult<()> {
+        self.gas_used = self.gas_used.saturating_add(cost);
+        if self.gas_used > self.transaction.gas_limit {
+            return Err(EvmError::OutOfGas);
+        }
+        Ok(())
+    }
+
+    /// Makes `len` bytes at `offset` addressable, charging for any growth
+    /// before allocating it. An empty range is free whatever its offset.
+    fn memory_range(&mut self, offset: U256, len: U256) -> EvmResult<(usize, usize)> {
+        if len.is_zero() {
+            return Ok((0, 0));
+        }
+        let offset = as_offset(offset)?;
+        let len = as_offset(len)?;
+        let new_words = gas::num_words(offset + len);
+        self.use_gas(gas::memory_expansion_cost(
+            self.memory.words() as u64,
+            new_words,
+        ))?;
+        self.memory.resize(offset, len);
+        Ok((offset, len))
+    }
+
+    pub fn pop(&mut self) -> EvmResult<TransparentU256> {
+        self.stack.pop().ok_or(EvmError::StackUnderflow)
+    }
+
+    pub fn add(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        // all arithmetic is modulo 2^256
+        let (res, _) = a.overflowing_add(*b);
+        self.stack.push(res.into());
+        Ok(())
+    }
+
+    pub fn mul(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        let (res, _) = a.overflowing_mul(*b);
+        self.stack.push(res.into());
+        Ok(())
+    }
+
+    pub fn sub(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        let (res, _) = a.overflowing_sub(*b);
+        self.stack.push(res.into());
+        Ok(())
+    }
+
+    pub fn div(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        // division by zero yields zero instead of failing
+        let res = a.checked_div(*b).unwrap_or_default();
+        self.stack.push(res.into());
+        Ok(())
+    }
+
+    pub fn sdiv(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        self.stack.push(i256_div(*a, *b).into());
+        Ok(())
+    }
+
+    pub fn r#mod(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        let res = a.checked_rem(*b).unwrap_or_default();
+        self.stack.push(res.into());
+        Ok(())
+    }
+
+    pub fn smod(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        self.stack.push(i256_mod(*a, *b).into());
+        Ok(())
+    }
+
+    pub fn addmod(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        let n = self.pop()?;
+        // the sum is taken in 512 bits so it is never truncated before the reduction
+        let res = if n.is_zero() {
+            U256::zero()
+        } else {
+            let sum = U512::from(*a) + U512::from(*b);
+            U256::try_from(sum % U512::from(*n)).expect("remainder is below the modulus")
+        };
+        self.stack.push(res.into());
+        Ok(())
+    }
+
+    pub fn mulmod(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        let n = self.pop()?;
+        let res = if n.is_zero() {
+            U256::zero()
+        } else {
+            let product = a.full_mul(*b);
+            U256::try_from(product % U512::from(*n)).expect("remainder is below the modulus")
+        };
+        self.stack.push(res.into());
+        Ok(())
+    }
+
+    pub fn exp(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        self.use_gas(gas::exp_cost(self.spec, &b))?;
+        let (res, _) = a.overflowing_pow(*b);
+        self.stack.push(res.into());
+        Ok(())
+    }
+
+    pub fn signextend(&mut self) -> EvmResult<()> {
+        let b = self.pop()?;
+        let x = self.pop()?;
+        self.stack.push(sign_extend(*b, *x).into());
+        Ok(())
+    }
+
+    pub fn lt(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        let res = if *a < *b { 1 } else { 0 };
+        self.stack.push(res.into());
+        Ok(())
+    }
+
+    pub fn eq(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        let res = if *b == *a { 1 } else { 0 };
+        self.stack.push(res.into());
+        Ok(())
+    }
+
+    pub fn gt(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        let res = if *a > *b { 1 } else { 0 };
+        self.stack.push(res.into());
+        Ok(())
+    }
+
+    pub fn slt(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        let res = if i256_cmp(&a, &b) == Ordering::Less {
+            1
+        } else {
+            0
+        };
+        self.stack.push(res.into());
+        Ok(())
+    }
+
+    pub fn sgt(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        let res = if i256_cmp(&a, &b) == Ordering::Greater {
+            1
+        } else {
+            0
+        };
+        self.stack.push(res.into());
+        Ok(())
+    }
+
+    pub fn iszero(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let res = if a.is_zero() { 1 } else { 0 };
+        self.stack.push(res.into());
+        Ok(())
+    }
+
+    pub fn and_op(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        self.stack.push(((*a) & (*b)).into());
+        Ok(())
+    }
+
+    pub fn or(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        self.stack.push((*a | *b).into());
+        Ok(())
+    }
+
+    pub fn xor(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        self.stack.push((*a ^ *b).into());
+        Ok(())
+    }
+
+    pub fn not(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        self.stack.push((!(*a)).into());
+        Ok(())
+    }
+
+    pub fn shl(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        if *a >= U256::from(256) {
+            self.stack.push(0.into());
+        } else {
+            self.stack.push((*b << a.as_usize()).into());
+        }
+        Ok(())
+    }
+
+    pub fn shr(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        if *a >= U256::from(256) {
+            self.stack.push(0.into());
+        } else {
+            self.stack.push((*b >> a.as_usize()).into());
+        }
+        Ok(())
+    }
+
+    pub fn sar(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        self.stack.push(i256_sar(*b, *a).into());
+        Ok(())
+    }
+
+    pub fn byte(&mut self) -> EvmResult<()> {
+        let a = self.pop()?;
+        let b = self.pop()?;
+        // byte 0 is the most significant one
+        if *a >= U256::from(32) {
+            self.stack.push(0.into());
+        } else {
+            self.stack.push((b.byte(31 - a.as_usize()) as u64).into());
+        }
+        Ok(())
+    }
+
+    pub fn mstore(&mut self) -> EvmResult<()> {
+        let offset = self.pop()?;
+        let value = self.pop()?;
+        let (offset, _) = self.memory_range(*offset, 32.into())?;
+        // 补充[u8;32]
+        let mut res: [u8; 32] = [0; 32];
+        value.to_big_endian(&mut res);
+        self.memory.set(offset, &res);
+        Ok(())
+    }
+
+    pub fn mstore8(&mut self) -> EvmResult<()> {
+        let offset = self.pop()?;
+        // only need low 8 bits
+        let value = self.pop()?;
+        let (offset, _) = self.memory_range(*offset, 1.into())?;
+        self.memory.set(offset, &[value.byte(0)]);
+        Ok(())
+    }
+
+    pub fn mload(&mut self) -> EvmResult<()> {
+        let offset = self.pop()?;
+        let (offset, _) = self.memory_range(*offset, 32.into())?;
+        let value = self.memory.get_slice(offset, 32);
+        self.stack.push(U256::from(value).into());
+        Ok(())
+    }
+
+    pub fn msize(&mut self) -> EvmResult<()> {
+        let size = self.memory.len() as u64;
+        self.stack.push(size.into());
+        Ok(())
+    }
+
+    pub fn sstore(&mut self) -> EvmResult<()> {
+        let key = self.pop()?;
+        let value = self.pop()?;
+        if self.spec.is_enabled(SpecId::Istanbul) && self.gas_left() <= gas::SSTORE_SENTRY {
+            return Err(EvmError::OutOfGas);
+        }
+        if self.state.access_storage(&self.transaction.this_addr, *key)
+            && self.spec.is_enabled(SpecId::Berlin)
+        {
+            self.use_gas(gas::COLD_SLOAD)?;
+        }
+        let this_addr = &self.transaction.this_addr;
+        let original = self.state.original_storage(this_addr, &key);
+        let current = self.state.get_storage(this_addr, &key);
+        self.use_gas(gas::sstore_cost(self.spec, &original, &current, &value))?;
+        let refund = gas::sstore_refund(self.spec, &original, &current, &value);
+        if refund != 0 {
+            self.state.add_refund(refund);
+        }
+        self.state
+            .set_storage(&self.transaction.this_addr, *key, *value);
+        Ok(())
+    }
+
+    pub fn sload(&mut self) -> EvmResult<()> {
+        let key = self.pop()?;
+        if self.state.access_storage(&self.transaction.this_addr, *key)
+            && self.spec.is_enabled(SpecId::Berlin)
+        {
+            self.use_gas(gas::COLD_SLOAD - gas::WARM_STORAGE_READ)?;
+        }
+        let value = self.state.get_storage(&self.transaction.this_addr, &key);
+        self.stack.push(value.into());
+        Ok(())
+    }
+
+    pub fn tstore(&mut self) -> EvmResult<()> {
+        let key = self.pop()?;
+        let value = self.pop()?;
+        self.state
+            .set_transient_storage(&self.transaction.this_addr, *key, *value);
+        Ok(())
+    }
+
+    pub fn tload(&mut self) -> EvmResult<()> {
+        let key = self.pop()?;
+        let value = self
+            .state
+            .get_transient_storage(&self.transaction.this_addr, &key);
+        self.stack.push(value.into());
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {}
+
+    pub fn find_valid_jump_destinations(&mut self) {
+        self.vaild_jump_dest = valid_jump_destinations(&self.code);
+    }
+
+    // empty func
+    pub fn jump_dest(&self) {}
+
+    // JUMP指令用于无条件跳转到一个新的程序计数器位置。它从堆栈中弹出一个元素，将这个元素设定为新的程序计数器（pc）的值。操作码是0x56，gas消耗为8
+    pub fn jump(&mut self) -> EvmResult<()> {
+        let dest = self.pop()?;
+        self.jump_to(&dest)
+    }
+
+    pub fn jumpi(&mut self) -> EvmResult<()> {
+        let dest = self.pop()?;
+        let op = self.pop()?;
+        if !op.is_zero() {
+            self.jump_to(&dest)?;
+        }
+        Ok(())
+    }
+
+    fn jump_to(&mut self, dest: &U256) -> EvmResult<()> {
+        if *dest >= U256::from(self.code.len()) {
+            return Err(EvmError::InvalidJump);
+        }
+        let dest = dest.as_usize();
+        if !self.vaild_jump_dest.contains(&dest) {
+            return Err(EvmError::InvalidJump);
+        }
+        self.pc = dest;
+        Ok(())
+    }
+
+    pub fn pc(&mut self) -> EvmResult<()> {
+        // self.pc already points past the PC instruction itself
+        self.stack.push(U256::from(self.pc - 1).into());
+        Ok(())
+    }
+
+    pub fn blockhash(&mut self) -> EvmResult<()> {
+        let block_number = self.pop()?;
+        if *block_number == U256::from(self.current_block.number) {
+            let block_hash = self.current_block.blockhash;
+            self.stack.push(block_hash.into());
+        } else {
+            self.stack.push(0.into())
+        }
+        Ok(())
+    }
+
+    pub fn coinbase(&mut self) -> EvmResult<()> {
+        self.stack.push(self.current_block.coinbase.into());
+        Ok(())
+    }
+
+    pub fn timestamp(&mut self) -> EvmResult<()> {
+        self.stack.push(self.current_block.timestamp.into());
+        Ok(())
+    }
+
+    pub fn number(&mut self) -> EvmResult<()> {
+        self.stack.push(self.current_block.number.into());
+        Ok(())
+    }
+
+    pub fn prevrandao(&mut self) -> EvmResult<()> {
+        // EIP-4399 reused DIFFICULTY for the beacon chain randomness
+        let value = if self.spec.is_enabled(SpecId::Merge) {
+            self.current_block.prevrandao
+        } else {
+            self.current_block.difficulty
+        };
+        self.stack.push(value.into());
+        Ok(())
+    }
+
+    pub fn gaslimit(&mut self) -> EvmResult<()> {
+        self.stack
+            .push(TransparentU256(self.current_block.gaslimit.get().into()));
+        Ok(())
+    }
+
+    pub fn chainid(&mut self) -> EvmResult<()> {
+        self.stack
+            .push(TransparentU256(self.current_block.chainid.into()));
+        Ok(())
+    }
+
+    pub fn selfbalance(&mut self) -> EvmResult<()> {
+        let balance = self.balance_of(&self.transaction.this_addr);
+        self.stack.push(balance.into());
+        Ok(())
+    }
+
+    pub fn basefee(&mut self) -> EvmResult<()> {
+        self.stack
+            .push(TransparentU256(self.current_block.basefee.get().into()));
+        Ok(())
+    }
+
+    pub fn blobhash(&mut self) -> EvmResult<()> {
+        let index = self.pop()?;
+        let hashes = &self.transaction.blob_versioned_hashes;
+        // indices past the last blob read as zero
+        let hash = if *index < U256::from(hashes.len()) {
+            hashes[index.as_usize()]
+        } else {
+            U256::zero()
+        };
+        self.stack.push(hash.into());
+        Ok(())
+    }
+
+    pub fn blobbasefee(&mut self) -> EvmResult<()> {
+        self.stack
+            .push(self.current_block.blob_basefee(self.spec).into());
+        Ok(())
+    }
+
+    pub fn dup(&mut self, postion: usize) -> EvmResult<()> {
+        if self.stack.len() < postion {
+            return Err(EvmError::StackUnderflow);
+        }
+        let value = self.stack[self.stack.len() - postion].clone();
+        self.stack.push(value);
+        Ok(())
+    }
+
+    pub fn swap(&mut self, postion: usize) -> EvmResult<()> {
+        if self.stack.len() < postion + 1 {
+            return Err(EvmError::StackUnderflow);
+        }
+        let idx1 = self.stack.len() - 1;
+        let idx2 = self.stack.len() - 1 - postion;
+        self.stack.swap(idx1, idx2);
+        Ok(())
+    }
+
+    pub fn sha3(&mut self) -> EvmResult<()> {
+        let offset = self.pop()?;
+        let size = self.pop()?;
+        let (offset, size) = self.memory_range(*offset, *size)?;
+        self.use_gas(gas::keccak256_cost(size))?;
+        let data = self.memory.get_slice(offset, size);
+        let mut hasher = sha3::Keccak256::new();
+        hasher.update(data);
+        let result = hasher.finalize();
+        self.stack.push(U256::from(&result[..]).into());
+        Ok(())
+    }
+
+    pub fn balance(&mut self) -> EvmResult<()> {
+        let address = self.pop()?;
+        self.access_account(&address)?;
+        let balance = self.balance_of(&address);
+        self.stack.push(balance.into());
+        Ok(())
+    }
+
+    pub fn extcodesize(&mut self) -> EvmResult<()> {
+        let address = self.pop()?;
+        self.access_account(&address)?;
+        let size = self.state.get_code(&address).len();
+        self.stack.push((size as u64).into());
+        Ok(())
+    }
+
+    pub fn extcodecopy(&mut self) -> EvmResult<()> {
+        let addr = self.pop()?;
+        self.access_account(&addr)?;
+        let (mem_offset, code_offset, length) = self.copy_args()?;
+        let code = self.state.get_code(&addr);
+        self.memory.set_data(mem_offset, code_offset, length, &code);
+        Ok(())
+    }
+
+    pub fn extcodehash(&mut self) -> EvmResult<()> {
+        let address = self.pop()?;
+        self.access_account(&address)?;
+        // non-existent and empty accounts hash to zero (EIP-1052)
+        let code_hash = self
+            .state
+            .get_account_info(&address)
+            .filter(|a| !a.is_empty())
+            .map_or(U256::zero(), |a| a.code_hash);
+        self.stack.push(code_hash.into());
+        Ok(())
+    }
+
+    pub fn address(&mut self) -> EvmResult<()> {
+        self.stack.push(self.transaction.this_addr.clone());
+        Ok(())
+    }
+
+    pub fn origin(&mut self) -> EvmResult<()> {
+        self.stack.push(self.transaction.origin.clone());
+        Ok(())
+    }
+
+    pub fn caller(&mut self) -> EvmResult<()> {
+        self.stack.push(self.transaction.caller.clone());
+        Ok(())
+    }
+
+    pub fn callvalue(&mut self) -> EvmResult<()> {
+        self.stack.push(self.transaction.value.into());
+        Ok(())
+    }
+
+    pub fn calldataload(&mut self) -> EvmResult<()> {
+        let offset = self.pop()?;
+        // reading past the end of calldata yields zero bytes
+        let mut word = [0u8; 32];
+        let data = &self.transaction.data;
+        if *offset < U256::from(data.len()) {
+            let offset = offset.as_usize();
+            let end = data.len().min(offset + 32);
+            word[..end - offset].copy_from_slice(&data[offset..end]);
+        }
+        self.stack.push(U256::from(&word[..]).into());
+        Ok(())
+    }
+
+    pub fn calldatasize(&mut self) -> EvmResult<()> {
+        self.stack.push((self.transaction.data.len() as u64).into());
+        Ok(())
+    }
+
+    pub fn calldatacopy(&mut self) -> EvmResult<()> {
+        let (mem_offset, data_offset, length) = self.copy_args()?;
+        self.memory
+            .set_data(mem_offset, data_offset, length, &self.transaction.data);
+        Ok(())
+    }
+
+    pub fn codesize(&mut self) -> EvmResult<()> {
+        self.stack.push((self.code.len() as u64).into());
+        Ok(())
+    }
+
+    pub fn codecopy(&mut self) -> EvmResult<()> {
+        let (mem_offset, code_offset, length) = self.copy_args()?;
+        self.memory
+            .set_data(mem_offset, code_offset, length, &self.code);
+        Ok(())
+    }
+
+    pub fn gasprice(&mut self) -> EvmResult<()> {
+        self.stack.push(self.transaction.gas_price.into());
+        Ok(())
+    }
+
+    /// LOG0 to LOG4: the topic count comes from the opcode, the stack holds
+    /// the data range followed by the topics.
+    pub fn log(&mut self, num_topics: usize) -> EvmResult<()> {
+        if self.stack.len() < 2 + num_topics {
+            return Err(EvmError::StackUnderflow);
+        }
+        let mem_offset = self.pop()?;
+        let length = self.pop()?;
+        let mut topics = Vec::with_capacity(num_topics);
+        for _ in 0..num_topics {
+            topics.push(self.pop()?);
+        }
+        let (mem_offset, length) = self.memory_range(*mem_offset, *length)?;
+        self.use_gas(gas::log_data_cost(length))?;
+        let log = EVMLog {
+            address: self.transaction.this_addr.clone(),
+            data: self.memory.get_slice(mem_offset, length).to_vec(),
+            topics,
+        };
+        self.state.log(log);
+        Ok(())
+    }
+
+    pub fn return_op(&mut self) -> EvmResult<()> {
+        let mem_offset = self.pop()?;
+        let length = self.pop()?;
+        let (mem_offset, length) = self.memory_range(*mem_offset, *length)?;
+        self.return_data = self.memory.get_slice(mem_offset, length).to_vec();
+        Ok(())
+    }
+
+    pub fn return_data_size(&mut self) -> EvmResult<()> {
+        self.stack
+            .push((self.return_data_buffer.len() as u64).into());
+        Ok(())
+    }
+
+    pub fn return_data_copy(&mut self) -> EvmResult<()> {
+        let (mem_offset, return_offset, length) = self.copy_args()?;
+        // unlike the other copies, reading past the end is an error
+        if return_offset.saturating_add(length.into()) > U256::from(self.return_data_buffer.len()) {
+            return Err(EvmError::ReturnDataOutOfBounds);
+        }
+        self.memory
+            .set_data(mem_offset, return_offset, length, &self.return_data_buffer);
+        Ok(())
+    }
+
+    pub fn mcopy(&mut self) -> EvmResult<()> {
+        let (dest, src, length) = self.copy_args()?;
+        // the source range has to be addressable too
+        let (src, _) = self.memory_range(src, length.into())?;
+        self.memory.copy_within(src, dest, length);
+        Ok(())
+    }
+
+    /// Pops the `destOffset offset size` arguments shared by every copy into
+    /// memory, charging the memory expansion and the per-word copy cost.
+    fn copy_args(&mut self) -> EvmResult<(usize, U256, usize)> {
+        let mem_offset = self.pop()?;
+        let offset = self.pop()?;
+        let length = self.pop()?;
+        let (mem_offset, length) = self.memory_range(*mem_offset, *length)?;
+        self.use_gas(gas::copy_cost(length))?;
+        Ok((mem_offset, *offset, length))
+    }
+
+    pub fn revert(&mut self) -> EvmResult<()> {
+        let mem_offset = self.pop()?;
+        let length = self.pop()?;
+        let (mem_offset, length) = self.memory_range(*mem_offset, *length)?;
+        self.return_data = self.memory.get_slice(mem_offset, length).to_vec();
+        self.success = false;
+        Ok(())
+    }
+
+    pub fn invalid(&mut self) -> EvmResult<()> {
+        Err(EvmError::InvalidOpcode(INVALID))
+    }
+
+    pub fn call(&mut self) -> EvmResult<()> {
+        self.call_op(CallKind::Call)
+    }
+
+    pub fn call_code(&mut self) -> EvmResult<()> {
+        self.call_op(CallKind::CallCode)
+    }
+
+    pub fn delegate_call(&mut self) -> EvmResult<()> {
+        self.call_op(CallKind::DelegateCall)
+    }
+
+    pub fn static_call(&mut self) -> EvmResult<()> {
+        self.call_op(CallKind::StaticCall)
+    }
+
+    /// Shared body of the CALL family. The code run is always the target's,
+    /// the kinds differ in the context it runs in:
+    ///
+    /// | kind         | caller        | address   | value         |
+    /// |--------------|---------------|-----------|---------------|
+    /// | CALL         | this contract | target    | popped        |
+    /// | CALLCODE     | this contract | this      | popped        |
+    /// | DELEGATECALL | our caller    | this      | our callvalue |
+    /// | STATICCALL   | this contract | target    | 0             |
+    fn call_op(&mut self, kind: CallKind) -> EvmResult<()> {
+        let requested_gas = self.pop()?;
+        let to_addr = self.pop()?;
+        let value = match kind {
+            CallKind::Call | CallKind::CallCode => *self.pop()?,
+            CallKind::DelegateCall | CallKind::StaticCall => U256::zero(),
+        };
+        self.access_account(&to_addr)?;
+
+        if kind == CallKind::Call && self.is_static && !value.is_zero() {
+            return Err(EvmError::StaticStateChange);
+        }
+
+        let mem_in_start = self.pop()?;
+        let mem_in_size = self.pop()?;
+        let mem_out_start = self.pop()?;
+        let mem_out_size = self.pop()?;
+
+        // 拓展内存
+        let (mem_in_start, mem_in_size) = self.memory_range(*mem_in_start, *mem_in_size)?;
+        let (mem_out_start, mem_out_size) = self.memory_range(*mem_out_start, *mem_out_size)?;
+        let data = self.memory.get_slice(mem_in_start, mem_in_size).to_vec();
+
+        let this_addr = self.transaction.this_addr.clone();
+        let (caller, target) = match kind {
+            CallKind::Call | CallKind::StaticCall => (this_addr, to_addr.clone()),
+            CallKind::CallCode => (this_addr.clone(), this_addr),
+            CallKind::DelegateCall => (self.transaction.caller.clone(), this_addr),
+        };
+
+        let transfers_value = !value.is_zero();
+        // EIP-161 only charges for an account the value brings to life,
+        // before that for any account the call touched into existence
+        let target_account = self.state.get_account_info(&target);
+        let creates_account = kind == CallKind::Call
+            && if self.spec.is_enabled(SpecId::SpuriousDragon) {
+                transfers_value && target_account.is_none_or(|a| a.is_empty())
+            } else {
+                target_account.is_none()
+            };
+        self.use_gas(gas::call_value_cost(transfers_value, creates_account))?;
+
+        // EIP-150: the callee gets what was asked for, but never more than
+        // all but one 64th of what is left. Before it, asking for more than
+        // is left runs out of gas.
+        let max_call_gas = self.max_call_gas();
+        let mut call_gas = if *requested_gas <= U256::from(max_call_gas) {
+            requested_gas.as_u64()
+        } else if self.spec.is_enabled(SpecId::TangerineWhistle) {
+            max_call_gas
+        } else {
+            return Err(EvmError::OutOfGas);
+        };
+        self.use_gas(call_gas)?;
+        if transfers_value {
+            // the stipend comes on top, the caller does not pay for it
+            call_gas += gas::CALL_STIPEND;
+        }
+
+        let this_addr = self.transaction.this_addr.clone();
+        // too deep a call or too little balance only fails the call, not the caller
+        if self.depth >= CALL_DEPTH_LIMIT
+            || (transfers_value && U256::from(self.balance_of(&this_addr)) < value)
+        {
+            self.return_gas(call_gas);
+            self.return_data_buffer.clear();
+            self.stack.push(0.into());
+            return Ok(());
+        }
+
+        let checkpoint = self.state.checkpoint();
+        let value = if kind == CallKind::DelegateCall {
+            // the value is only forwarded, it was already moved by our caller
+            self.transaction.value
+        } else {
+            if transfers_value {
+                self.transfer(&this_addr, &target, value);
+            }
+            value.as_u64()
+        };
+
+        let pending = Pending::Call {
+            checkpoint,
+            out_start: mem_out_start,
+            out_size: mem_out_size,
+        };
+        // every kind of call runs a precompile on the call data alone
+        let output = self
+            .precompiles
+            .get(&to_addr, self.spec)
+            .map(|precompile| precompile.run(&data, call_gas, self.spec));
+        match output {
+            Some(Ok(result)) if result.gas_used <= call_gas => {
+                self.resume(pending, true, result.output, call_gas - result.gas_used);
+            }
+            // a failed precompile, or one that claims more gas than it was
+            // given, consumes all of it
+            Some(_) => self.resume(pending, false, Vec::new(), 0),
+            None => {
+                let code = self.state.get_code(&to_addr);
+                let txn = Transaction {
+                    data,
+                    value,
+                    caller,
+                    this_addr: target,
+                    gas_limit: call_gas,
+                    ..self.frame_transaction()
+                };
+                let is_static = self.is_static || kind == CallKind::StaticCall;
+                let callee = Frame::new(code, txn, is_static, self.depth + 1);
+                self.enter(callee, pending);
+            }
+        }
+        Ok(())
+    }
+
+    /// Suspends the running frame and starts `callee` in its place. The
+    /// caller picks up again in `resume` once the callee halts.
+    fn enter(&mut self, callee: Frame, pending: Pending) {
+        let caller = self.switch_frame(callee);
+        self.callers.push((caller, pending));
+    }
+
+    /// Makes `frame` the running one and returns the frame it replaces.
+    fn switch_frame(&mut self, frame: Frame) -> Frame {
+        Frame {
+            code: mem::replace(&mut self.code, frame.code),
+            pc: mem::replace(&mut self.pc, frame.pc),
+            stack: mem::replace(&mut self.stack, frame.stack),
+            memory: mem::replace(&mut self.memory, frame.memory),
+            vaild_jump_dest: mem::replace(&mut self.vaild_jump_dest, frame.vaild_jump_dest),
+            transaction: mem::replace(&mut self.transaction, frame.transaction),
+            return_data: mem::replace(&mut self.return_data, frame.return_data),
+            return_data_buffer: mem::replace(
+                &mut self.return_data_buffer,
+                frame.return_data_buffer,
+            ),
+            success: mem::replace(&mut self.success, frame.success),
+            is_static: mem::replace(&mut self.is_static, frame.is_static),
+            gas_used: mem::replace(&mut self.gas_used, frame.gas_used),
+            depth: mem::replace(&mut self.depth, frame.depth),
+        }
+    }
+
+    /// Finishes the CALL or CREATE of the running frame with what the frame
+    /// it started left: whether it succeeded, its output and its unused gas.
+    /// A failed sub-call only pushes 0, the caller keeps running.
+    fn resume(&mut self, pending: Pending, success: bool, output: Vec<u8>, gas_left: u64) {
+        let (checkpoint, out_start, out_size) = match pending {
+            Pending::Call {
+                checkpoint,
+                out_start,
+                out_size,
+            } => (checkpoint, out_start, out_size),
+            Pending::Create {
+                checkpoint,
+                address,
+            } => return self.deploy(checkpoint, address, success, output, gas_left),
+        };
+        self.return_gas(gas_left);
+        if !success {
+            // undoes the value transfer as well as everything the callee did
+            self.state.revert_to(checkpoint);
+        }
+
+        let copy_size = out_size.min(output.len());
+        self.memory.set(out_start, &output[..copy_size]);
+        self.return_data_buffer = output;
+
+        if success {
+            self.stack.push(1.into());
+        } else {
+            self.stack.push(0.into());
+        }
+    }
+
+    /// Transaction for a child frame, carrying over what belongs to the
+    /// whole transaction rather than to a single call.
+    fn frame_transaction(&self) -> Transaction {
+        Transaction {
+            origin: self.transaction.origin.clone(),
+            gas_price: self.transaction.gas_price,
+            blob_versioned_hashes: self.transaction.blob_versioned_hashes.clone(),
+            max_fee_per_blob_gas: self.transaction.max_fee_per_blob_gas,
+            ..Transaction::default()
+        }
+    }
+
+    fn is_state_changing_opcode(&self, opcode: u8) -> bool {
+        let state_changing_opcodes = [
+            0xF0, // CREATE
+            0xF5, // CREATE2
+            0xFF, // SELFDESTRUCT
+            0xA0, // LOG0,
+            0xA1, // LOG1
+            0xA2, // LOG2
+            0xA3, // LOG3
+            0xA4, // LOG4
+            0x55, // SSTORE
+            0x5D, // TSTORE
+        ];
+        state_changing_opcodes.contains(&opcode)
+    }
+
+    pub fn create(&mut self) -> EvmResult<()> {
+        let value = self.pop()?;
+        let mem_offset = self.pop()?;
+        let length = self.pop()?;
+        let (mem_offset, length) = self.memory_range(*mem_offset, *length)?;
+        self.charge_initcode(length)?;
+
+        let nonce = self
+            .state
+            .get_account_info(&self.transaction.this_addr)
+            .map_or(0, |a| a.nonce);
+        let address = create_address(&self.transaction.this_addr, nonce);
+        self.create_contract(*value, mem_offset, length, address)
+    }
+
+    pub fn create2(&mut self) -> EvmResult<()> {
+        let value = self.pop()?;
+        let mem_offset = self.pop()?;
+        let length = self.pop()?;
+        let salt = self.pop()?;
+        let (mem_offset, length) = self.memory_range(*mem_offset, *length)?;
+        // CREATE2 also pays for hashing the init code into the address
+        self.use_gas(gas::keccak256_cost(length))?;
+        self.charge_initcode(length)?;
+
+        let init_code = self.memory.get_slice(mem_offset, length);
+        let address = create2_address(&self.transaction.this_addr, &salt, init_code);
+        self.create_contract(*value, mem_offset, length, address)
+    }
+
+    /// Since Shanghai init code is capped at twice the deployed code limit
+    /// and paid per word (EIP-3860).
+    fn charge_initcode(&mut self, length: usize) -> EvmResult<()> {
+        if !self.spec.is_enabled(SpecId::Shanghai) {
+            return Ok(());
+        }
+        if length > 2 * MAX_CODE_SIZE {
+            return Err(EvmError::InitcodeTooLarge(length));
+        }
+        self.use_gas(gas::initcode_cost(length))
+    }
+
+    /// Starts a child frame running the init code in
+    /// `memory[mem_offset..mem_offset + length]`, which the caller has already
+    /// expanded memory for. Pushes 0 if the creation fails before that.
+    fn create_contract(
+        &mut self,
+        value: U256,
+        mem_offset: usize,
+        length: usize,
+        address: TransparentU256,
+    ) -> EvmResult<()> {
+        let init_code = self.memory.get_slice(mem_offset, length).to_vec();
+        self.return_data_buffer.clear();
+
+        let sender = self.transaction.this_addr.clone();
+        let nonce = self.state.get_account_info(&sender).map_or(0, |a| a.nonce);
+        if self.depth >= CALL_DEPTH_LIMIT
+            || U256::from(self.balance_of(&sender)) < value
+            || nonce == u64::MAX
+        {
+            self.stack.push(0.into());
+            return Ok(());
+        }
+        // the sender's nonce stays bumped and the address warm even if the
+        // creation fails
+        self.state.set_nonce(&sender, nonce + 1);
+        self.state.access_account(&address);
+        let checkpoint = self.state.checkpoint();
+
+        // from here on a failed creation consumes the gas it was given
+        let create_gas = self.max_call_gas();
+        self.use_gas(create_gas)?;
+
+        // an address that already has code or a nonce cannot be deployed to
+        if let Some(existing) = self.state.get_account_info(&address) {
+            if existing.nonce != 0 || existing.has_code() {
+                self.stack.push(0.into());
+                return Ok(());
+            }
+        }
+
+        self.state.set_nonce(&address, self.created_nonce());
+        self.state.mark_created(&address);
+        self.transfer(&sender, &address, value);
+        let value = value.as_u64();
+
+        let txn = Transaction {
+            data: Vec::new(),
+            value,
+            caller: self.transaction.this_addr.clone(),
+            this_addr: address.clone(),
+            gas_limit: create_gas,
+            ..self.frame_transaction()
+        };
+        let callee = Frame::new(init_code, txn, false, self.depth + 1);
+        self.enter(
+            callee,
+            Pending::Create {
+                checkpoint,
+                address,
+            },
+        );
+        Ok(())
+    }
+
+    /// Installs the code a finished init code returned at `address` and
+    /// pushes the address, or 0 if the creation failed.
+    fn deploy(
+        &mut self,
+        checkpoint: Checkpoint,
+        address: TransparentU256,
+        success: bool,
+        output: Vec<u8>,
+        gas_left: u64,
+    ) {
+        let deployed = if success {
+            Some(output)
+        } else {
+            // a reverted constructor hands its revert data and unused gas
+            // back to the creator
+            self.return_data_buffer = output;
+            self.return_gas(gas_left);
+            None
+        };
+        // storing the code costs gas too, paid from what the constructor left
+        // over. Before Homestead (EIP-2) running short of it only leaves the
+        // contract without code.
+        let deposit = deployed
+            .filter(|code| self.check_deployable(code).is_ok())
+            .map(|code| (gas::code_deposit_cost(code.len()), code))
+            .filter(|(cost, _)| *cost <= gas_left || !self.spec.is_enabled(SpecId::Homestead));
+        let Some((cost, code)) = deposit else {
+            self.state.revert_to(checkpoint);
+            self.stack.push(0.into());
+            return;
+        };
+
+        if cost <= gas_left {
+            self.return_gas(gas_left - cost);
+            self.state.set_code(&address, code);
+        } else {
+            self.return_gas(gas_left);
+        }
+        self.stack.push(address);
+    }
+
+    /// Sends the whole balance to the beneficiary. Since EIP-6780 the
+    /// contract itself is only deleted if this transaction created it.
+    pub fn selfdestruct(&mut self) -> EvmResult<()> {
+        let beneficiary = self.pop()?;
+        // SELFDESTRUCT has no warm price, only the cold surcharge
+        if self.state.access_account(&beneficiary) && self.spec.is_enabled(SpecId::Berlin) {
+            self.use_gas(gas::COLD_ACCOUNT_ACCESS)?;
+        }
+        let this_addr = self.transaction.this_addr.clone();
+        let balance = self.balance_of(&this_addr);
+        let beneficiary_account = self.state.get_account_info(&beneficiary);
+        let creates_account = if self.spec.is_enabled(SpecId::SpuriousDragon) {
+            balance > 0 && beneficiary_account.is_none_or(|a| a.is_empty())
+        } else {
+            self.spec.is_enabled(SpecId::TangerineWhistle) && beneficiary_account.is_none()
+        };
+        if creates_account {
+            self.use_gas(gas::NEW_ACCOUNT)?;
+        }
+
+        if beneficiary != this_addr {
+            self.transfer(&this_addr, &beneficiary, balance.into());
+        }
+        if !self.spec.is_enabled(SpecId::Cancun) || self.state.is_created(&this_addr) {
+            // with itself as beneficiary the balance is burnt
+            self.state.set_balance(&this_addr, 0);
+            if self.state.selfdestruct(&this_addr) && !self.spec.is_enabled(SpecId::London) {
+                self.state.add_refund(gas::SELFDESTRUCT_REFUND);
+            }
+        }
+        Ok(())
+    }
+
+    /// Charges the EIP-2929 cold surcharge if this is the first access to
+    /// `address` in the transaction. The warm price is part of the opcode's
+    /// static cost.
+    fn access_account(&mut self, address: &TransparentU256) -> EvmResult<()> {
+        if self.state.access_account(address) && self.spec.is_enabled(SpecId::Berlin) {
+            self.use_gas(gas::COLD_ACCOUNT_ACCESS - gas::WARM_STORAGE_READ)?;
+        }
+        Ok(())
+    }
+
+    /// Warms what every transaction may touch for free: the sender, the
+    /// recipient, the coinbase (EIP-3651, since Shanghai), the precompiles
+    /// and whatever the EIP-2930 access list declares.
+    fn warm_transaction(&mut self) {
+        let mut accounts = vec![
+            self.transaction.origin.clone(),
+            self.transaction.caller.clone(),
+            self.transaction.this_addr.clone(),
+        ];
+        if self.spec.is_enabled(SpecId::Shanghai) {
+            accounts.push(self.current_block.coinbase.into());
+        }
+        accounts.extend(self.precompiles.addresses(self.spec).cloned());
+        for address in &accounts {
+            self.state.access_account(address);
+        }
+        for item in &self.transaction.access_list {
+            self.state.access_account(&item.address);
+            for slot in &item.storage_keys {
+                self.state.access_storage(&item.address, *slot);
+            }
+        }
+    }
+
+    /// Nonce a new contract starts with, 1 since EIP-161.
+    fn created_nonce(&self) -> u64 {
+        if self.spec.is_enabled(SpecId::SpuriousDragon) {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// Checks that `code` returned by init code may be deployed: EIP-170
+    /// caps its size and EIP-3541 rejects a leading 0xEF.
+    fn check_deployable(&self, code: &[u8]) -> EvmResult<()> {
+        if code.len() > MAX_CODE_SIZE && self.spec.is_enabled(SpecId::SpuriousDragon) {
+            return Err(EvmError::CodeTooLarge(code.len()));
+        }
+        if code.first() == Some(&EOF_MAGIC_PREFIX) && self.spec.is_enabled(SpecId::London) {
+            return Err(EvmError::InvalidCodePrefix);
+        }
+        Ok(())
+    }
+
+    fn balance_of(&self, address: &TransparentU256) -> u64 {
+        self.state
+            .get_account_info(address)
+            .map_or(0, |a| a.balance)
+    }
+
+    /// Moves `value` wei between two accounts, returning false without
+    /// touching either if `from` cannot afford it. A balance that would pass
+    /// `u64::MAX` stays at the maximum.
+    fn transfer(&mut self, from: &TransparentU256, to: &TransparentU256, value: U256) -> bool {
+        let from_balance = self.balance_of(from);
+        if U256::from(from_balance) < value {
+            return false;
+        }
+        let value = value.as_u64();
+        self.state.set_balance(from, from_balance - value);
+        let to_balance = self.balance_of(to);
+        self.state.set_balance(to, to_balance.saturating_add(value));
+        true
+    }
+
+    pub fn gas(&mut self) -> EvmResult<()> {
+        self.stack.push(self.gas_left().into());
+        Ok(())
+    }
+
+    /// Blob gas the transaction buys, zero before Cancun.
+    fn blob_gas(&self) -> u64 {
+        if !self.spec.is_enabled(SpecId::Cancun) {
+            return 0;
+        }
+        gas::GAS_PER_BLOB * self.transaction.blob_versioned_hashes.len() as u64
+    }
+
+    /// Fee for the blob gas, burnt at the blob base fee whatever the sender
+    /// offered. Only called once validation checked that the sender's
+    /// `max_fee_per_blob_gas` covers it.
+    fn blob_fee(&self) -> u64 {
+        let blob_gas = self.blob_gas();
+        if blob_gas == 0 {
+            return 0;
+        }
+        u64::try_from(self.current_block.blob_basefee(self.spec))
+            .ok()
+            .and_then(|blob_basefee| blob_gas.checked_mul(blob_basefee))
+            .expect("blob fee is below the validated maximum")
+    }
+
+    /// Checks that the transaction could be included in the current block
+    /// and returns its intrinsic gas, the part paid before any code runs.
+    pub fn validate_transaction(&self) -> Result<u64, InvalidTransaction> {
+        let txn = &self.transaction;
+        let payload = if txn.is_create { &self.code } else { &txn.data };
+        // EIP-3860 limits init code to twice the deployed code limit
+        if txn.is_create
+            && self.spec.is_enabled(SpecId::Shanghai)
+            && payload.len() > 2 * MAX_CODE_SIZE
+        {
+            return Err(InvalidTransaction::InitcodeTooLarge(payload.len()));
+        }
+        let intrinsic_gas = gas::intrinsic_gas(self.spec, payload, txn.is_create, &txn.access_list);
+        // the gas limit has to cover the EIP-7623 floor as well
+        let required_gas = if self.spec.is_enabled(SpecId::Prague) {
+            intrinsic_gas.max(gas::floor_gas(payload))
+        } else {
+            intrinsic_gas
+        };
+        if txn.gas_limit < required_gas {
+            return Err(InvalidTransaction::IntrinsicGasTooLow {
+                gas_limit: txn.gas_limit,
+                intrinsic_gas: required_gas,
+            });
+        }
+        let block_gas_limit = self.current_block.gaslimit.get().into();
+        if txn.gas_limit > block_gas_limit {
+            return Err(InvalidTransaction::GasLimitExceedsBlock {
+                gas_limit: txn.gas_limit,
+                block_gas_limit,
+            });
+        }
+        let basefee = self.current_block.basefee.get().into();
+        if self.spec.is_enabled(SpecId::London) && txn.gas_price < basefee {
+            return Err(InvalidTransaction::GasPriceBelowBaseFee {
+                gas_price: txn.gas_price,
+                basefee,
+            });
+        }
+        let blob_gas = self.blob_gas();
+        if blob_gas > 0 {
+            if let Some(hash) = txn
+                .blob_versioned_hashes
+                .iter()
+                .find(|hash| hash.byte(31) != VERSIONED_HASH_VERSION_KZG)
+            {
+                return Err(InvalidTransaction::InvalidBlobVersionedHash(*hash));
+            }
+            let blob_basefee = self.current_block.blob_basefee(self.spec);
+            if U256::from(txn.max_fee_per_blob_gas) < blob_basefee {
+                return Err(InvalidTransaction::BlobFeeBelowBaseFee {
+                    max_fee: txn.max_fee_per_blob_gas,
+                    blob_basefee,
+                });
+            }
+        }
+
+        let sender = self.state.get_account_info(&txn.caller);
+        let expected = sender.map_or(0, |a| a.nonce);
+        if txn.nonce != expected {
+            return Err(InvalidTransaction::NonceMismatch {
+                expected,
+                got: txn.nonce,
+            });
+        }
+        if expected == u64::MAX {
+            return Err(InvalidTransaction::NonceOverflow);
+        }
+        let balance = sender.map_or(0, |a| a.balance);
+        let cost = txn
+            .gas_limit
+            .checked_mul(txn.gas_price)
+            .and_then(|fee| fee.checked_add(txn.value))
+            .and_then(|cost| {
+                blob_gas
+                    .checked_mul(txn.max_fee_per_blob_gas)
+                    .and_then(|blob_fee| cost.checked_add(blob_fee))
+            });
+        // a cost that does not even fit into a u64 is never covered
+        match cost {
+            Some(cost) if balance >= cost => {}
+            _ => {
+                return Err(InvalidTransaction::InsufficientFunds {
+                    balance,
+                    cost: cost.unwrap_or(u64::MAX),
+                })
+            }
+        }
+        Ok(intrinsic_gas)
+    }
+
+    /// Runs the code as a whole transaction sent by `transaction.caller`,
+    /// which is also the origin every frame sees. Once validated, the
+    /// sender's nonce is bumped, the gas bought up front, the value moved and
+    /// the code run; a creation also deploys what the code returns. A failed
+    /// run is rolled back, but the fee stays paid. Unused gas and the capped
+    /// refund go back to the sender, the coinbase gets the priority fee and
+    /// the state is committed.
+    ///
+    /// Only a transaction that cannot be included is an error. One that
+    /// halts exceptionally is still charged and comes back unsuccessful,
+    /// with the reason in `halt_reason`.
+    pub fn transact(&mut self) -> Result<ExecutionResult, InvalidTransaction> {
+        let intrinsic_gas = self.validate_transaction()?;
+        self.state.begin_transaction();
+
+        let sender = self.transaction.caller.clone();
+        let nonce = self.transaction.nonce;
+        self.transaction.origin = sender.clone();
+        if self.transaction.is_create {
+            self.transaction.this_addr = create_address(&sender, nonce);
+        }
+        self.warm_transaction();
+        self.state.set_nonce(&sender, nonce + 1);
+        let gas_fee = self.transaction.gas_limit * self.transaction.gas_price;
+        let blob_fee = self.blob_fee();
+        let balance = self.balance_of(&sender);
+        self.state
+            .set_balance(&sender, balance - gas_fee - blob_fee);
+        self.gas_used = intrinsic_gas;
+
+        let checkpoint = self.state.checkpoint();
+        let this_addr = self.transaction.this_addr.clone();
+        if self.transaction.is_create {
+            self.state.set_nonce(&this_addr, self.created_nonce());
+            self.state.mark_created(&this_addr);
+        }
+        self.transfer(&sender, &this_addr, self.transaction.value.into());
+        let mut halt_reason = self.run().err();
+        if halt_reason.is_none() && self.success && self.transaction.is_create {
+            let code = std::mem::take(&mut self.return_data);
+            let deposit = gas::code_deposit_cost(code.len());
+            let deployed = self.check_deployable(&code).and_then(|()| {
+                if deposit > self.gas_left() && self.spec.is_enabled(SpecId::Homestead) {
+                    return Err(EvmError::OutOfGas);
+                }
+                Ok(())
+            });
+            match deployed {
+                // before Homestead a deposit the gas cannot cover is skipped
+                Ok(()) if deposit > self.gas_left() => {}
+                Ok(()) => {
+                    self.gas_used += deposit;
+                    self.state.set_code(&this_addr, code);
+                }
+                Err(err) => {
+                    self.success = false;
+                    self.gas_used = self.transaction.gas_limit;
+                    halt_reason = Some(err);
+                }
+            }
+        }
+        if !self.success {
+            self.state.revert_to(checkpoint);
+        }
+
+        let refund =
+            (self.state.refund().max(0) as u64).min(gas::max_refund(self.spec, self.gas_used));
+        self.gas_used -= refund;
+        if self.spec.is_enabled(SpecId::Prague) {
+            let payload = if self.transaction.is_create {
+                &self.code
+            } else {
+                &self.transaction.data
+            };
+            self.gas_used = self.gas_used.max(gas::floor_gas(payload));
+        }
+        self.settle(&sender);
+        self.state.commit();
+
+        Ok(ExecutionResult {
+            halt_reason,
+            ..self.result()
+        })
+    }
+
+    /// Returns the unused gas to the sender and pays the coinbase its
+    /// priority fee. Since London the base fee part is burnt.
+    fn settle(&mut self, sender: &TransparentU256) {
+        let gas_price = self.transaction.gas_price;
+        let unused = self.transaction.gas_limit - self.gas_used;
+        let balance = self.balance_of(sender);
+        self.state
+            .set_balance(sender, balance.saturating_add(unused * gas_price));
+
+        let priority_fee = if self.spec.is_enabled(SpecId::London) {
+            gas_price.saturating_sub(self.current_block.basefee.get().into())
+        } else {
+            gas_price
+        };
+        let reward = self.gas_used * priority_fee;
+        if reward > 0 {
+            let coinbase = self.current_block.coinbase.into();
+            let balance = self.balance_of(&coinbase);
+            self.state
+                .set_balance(&coinbase, balance.saturating_add(reward));
+        }
+    }
+
+    /// Runs the code of this frame, and of every frame it starts, until it
+    /// halts. An exceptional halt reverts the frame: the return data is
+    /// dropped and all gas given to it is consumed. Undoing its state changes
+    /// is up to whoever started it.
+    pub fn run(&mut self) -> EvmResult<ExecutionResult> {
+        if let Err(err) = self.execute() {
+            self.halt_exceptionally();
+            return Err(err);
+        }
+        Ok(self.result())
+    }
+
+    fn halt_exceptionally(&mut self) {
+        self.success = false;
+        self.return_data.clear();
+        self.gas_used = self.transaction.gas_limit;
+    }
+
+    fn result(&self) -> ExecutionResult {
+        ExecutionResult {
+            success: self.success,
+            gas_used: self.gas_used,
+            return_data: self.return_data.clone(),
+            halt_reason: None,
+        }
+    }
+
+    /// Runs frames until the outermost one halts. CALL and CREATE do not
+    /// recurse into a new interpreter but switch to the callee's frame, and
+    /// its caller resumes here once it halts, so nesting calls costs heap
+    /// rather than native stack.
+    fn execute(&mut self) -> EvmResult<()> {
+        loop {
+            let halted = self.execute_frame();
+            let Some((caller, pending)) = self.callers.pop() else {
+                return halted;
+            };
+            if halted.is_err() {
+                self.halt_exceptionally();
+            }
+            let callee = self.switch_frame(caller);
+            let gas_left = callee.transaction.gas_limit.saturating_sub(callee.gas_used);
+            self.resume(pending, callee.success, callee.return_data, gas_left);
+        }
+    }
+
+    /// Steps the running frame until it halts. CALL and CREATE switch to the
+    /// callee, which is then stepped in its place.
+    fn execute_frame(&mut self) -> EvmResult<()> {
+        while self.pc < self.code.len() {
+            let op = self.next_instruction();
+            if !self.spec.has_opcode(op) {
+                return Err(EvmError::InvalidOpcode(op));
+            }
+            if self.is_static && self.is_state_changing_opcode(op) {
+                return Err(EvmError::StaticStateChange);
+            }
+            if let Some(cost) = gas::static_cost(op, self.spec) {
+                self.use_gas(cost)?;
+            }
+            match op {
+                i if (PUSH1..=PUSH32).contains(&i) => {
+                    let size = op - PUSH1 + 1;
+                    self.push(size as usize)?;
+                }
+                PUSH0 => self.stack.push(0.into()),
+                POP => {
+                    self.pop()?;
+                }
+                ADD => {
+                    self.add()?;
+                }
+                MUL => {
+                    self.mul()?;
+                }
+                SUB => {
+                    self.sub()?;
+                }
+                DIV => {
+                    self.div()?;
+                }
+                SDIV => {
+                    self.sdiv()?;
+                }
+                MOD => {
+                    self.r#mod()?;
+                }
+                SMOD => {
+                    self.smod()?;
+                }
+                ADDMOD => {
+                    self.addmod()?;
+                }
+                MULMOD => {
+                    self.mulmod()?;
+                }
+                EXP => {
+                    self.exp()?;
+                }
+                SIGNEXTEND => {
+                    self.signextend()?;
+                }
+                LT => {
+                    self.lt()?;
+                }
+                GT => {
+                    self.gt()?;
+                }
+                SLT => {
+                    self.slt()?;
+                }
+                SGT => {
+                    self.sgt()?;
+                }
+                EQ => {
+                    self.eq()?;
+                }
+                ISZERO => {
+                    self.iszero()?;
+                }
+                AND => {
+                    self.and_op()?;
+                }
+                OR => {
+                    self.or()?;
+                }
+                XOR => {
+                    self.xor()?;
+                }
+                NOT => {
+                    self.not()?;
+                }
+                SHL => {
+                    self.shl()?;
+                }
+                SHR => {
+                    self.shr()?;
+                }
+                SAR => {
+                    self.sar()?;
+                }
+                BYTE => {
+                    self.byte()?;
+                }
+                MSTORE => {
+                    self.mstore()?;
+                }
+                MSTORE8 => {
+                    self.mstore8()?;
+                }
+                MLOAD => {
+                    self.mload()?;
+                }
+                MSIZE => {
+                    self.msize()?;
+                }
+                SSTORE => {
+                    self.sstore()?;
+                }
+                SLOAD => {
+                    self.sload()?;
+                }
+                TSTORE => {
+                    self.tstore()?;
+                }
+                TLOAD => {
+                    self.tload()?;
+                }
+                STOP => {
+                    self.stop();
+                    break;
+                }
+                JUMP => {
+                    self.jump()?;
+                }
+                JUMPDEST => {
+                    self.jump_dest();
+                }
+                PC => self.pc()?,
+                JUMPI => {
+                    self.jumpi()?;
+                }
+                BLOCKHASH => {
+                    self.blockhash()?;
+                }
+                COINBASE => {
+                    self.coinbase()?;
+                }
+                TIMESTAMP => {
+                    self.timestamp()?;
+                }
+                NUMBER => self.number()?,
+                PREVRANDAO => {
+                    self.prevrandao()?;
+                }
+                GASLIMIT => {
+                    self.gaslimit()?;
+                }
+                CHAINID => {
+                    self.chainid()?;
+                }
+                SELFBALANCE => {
+                    self.selfbalance()?;
+                }
+                BASEFEE => {
+                    self.basefee()?;
+                }
+                BLOBHASH => {
+                    self.blobhash()?;
+                }
+                BLOBBASEFEE => {
+                    self.blobbasefee()?;
+                }
+                i if (DUP1..=DUP16).contains(&i) => {
+                    let position = i - DUP1 + 1;
+                    self.dup(position as usize)?;
+                }
+                i if (SWAP1..=SWAP16).contains(&i) => {
+                    let position = op - SWAP1 + 1;
+                    self.swap(position as usize)?;
+                }
+                SHA3 => {
+                    self.sha3()?;
+                }
+                BALANCE => {
+                    self.balance()?;
+                }
+                EXTCODESIZE => {
+                    self.extcodesize()?;
+                }
+                EXTCODECOPY => {
+                    self.extcodecopy()?;
+                }
+                EXTCODEHASH => {
+                    self.extcodehash()?;
+                }
+                ADDRESS => {
+                    self.address()?;
+                }
+                ORIGIN => {
+                    self.origin()?;
+                }
+                CALLER => {
+                    self.caller()?;
+                }
+                CALLVALUE => {
+                    self.callvalue()?;
+                }
+                CALLDATALOAD => {
+                    self.calldataload()?;
+                }
+                CALLDATASIZE => {
+                    self.calldatasize()?;
+                }
+                CALLDATACOPY => {
+                    self.calldatacopy()?;
+                }
+                CODESIZE => {
+                    self.codesize()?;
+                }
+                CODECOPY => {
+                    self.codecopy()?;
+                }
+                GASPRICE => {
+                    self.gasprice()?;
+                }
+                i if (LOG0..=LOG4).contains(&i) => {
+                    let num_topics = i - LOG0;
+                    self.log(num_topics as usize)?;
+                }
+                RETURN => {
+                    self.return_op()?;
+                    break;
+                }
+                RETURNDATASIZE => {
+                    self.return_data_size()?;
+                }
+                MCOPY => {
+                    self.mcopy()?;
+                }
+                RETURNDATACOPY => {
+                    self.return_data_copy()?;
+                }
+                REVERT => {
+                    self.revert()?;
+                    break;
+                }
+                INVALID => {
+                    self.invalid()?;
+                }
+                CALL => {
+                    self.call()?;
+                }
+                CALLCODE => {
+                    self.call_code()?;
+                }
+                DELEGATECALL => {
+                    self.delegate_call()?;
+                }
+                STATICCALL => {
+                    self.static_call()?;
+                }
+                CREATE => {
+                    self.create()?;
+                }
+                CREATE2 => {
+                    self.create2()?;
+                }
+                SELFDESTRUCT => {
+                    self.selfdestruct()?;
+                    break;
+                }
+                GAS => {
+                    self.gas()?;
+                }
+                _ => return Err(EvmError::InvalidOpcode(op)),
+            }
+            if self.stack.len() > STACK_LIMIT {
+                return Err(EvmError::StackOverflow);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CallKind {
+    Call,
+    CallCode,
+    DelegateCall,
+    StaticCall,
+}
+
+/// Everything a call frame does not share with the rest of the
+/// transaction. The running frame's lives in `EVM` itself.
+struct Frame {
+    code: Vec<u8>,
+    pc: usize,
+    stack: Vec<TransparentU256>,
+    memory: Memory,
+    vaild_jump_dest: HashSet<usize>,
+    transaction: Transaction,
+    return_data: Vec<u8>,
+    return_data_buffer: Vec<u8>,
+    success: bool,
+    is_static: bool,
+    gas_used: u64,
+    depth: usize,
+}
+
+impl Frame {
+    fn new(code: Vec<u8>, transaction: Transaction, is_static: bool, depth: usize) -> Self {
+        Self {
+            vaild_jump_dest: valid_jump_destinations(&code),
+            code,
+            pc: 0,
+            stack: Vec::with_capacity(256),
+            memory: Memory::new(),
+            transaction,
+            return_data: Vec::new(),
+            return_data_buffer: Vec::new(),
+            success: true,
+            is_static,
+            gas_used: 0,
+            depth,
+        }
+    }
+}
+
+/// What a suspended frame still has to do once the frame it started halts.
+enum Pending {
+    // copy the output into memory[out_start..out_start + out_size]
+    Call {
+        checkpoint: Checkpoint,
+        out_start: usize,
+        out_size: usize,
+    },
+    // deploy the returned code at `address`
+    Create {
+        checkpoint: Checkpoint,
+        address: TransparentU256,
+    },
+}
+
+fn valid_jump_destinations(code: &[u8]) -> HashSet<usize> {
+    let mut dests = HashSet::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        let op = code[pc];
+        if op == JUMPDEST {
+            dests.insert(pc);
+        } else if (PUSH1..=PUSH32).contains(&op) {
+            // skip the immediate
+            pc += (op - PUSH1 + 1) as usize;
+        }
+        pc += 1;
+    }
+    dests
+}
+
+// 在堆栈中，每个元素长度为256位 最大深度1024
+const STACK_LIMIT: usize = 1024;
+// deepest a CALL or CREATE may nest
+const CALL_DEPTH_LIMIT: usize = 1024;
+// EIP-170 limit on deployed code
+const MAX_CODE_SIZE: usize = 0x6000;
+// EIP-3541 reserves code starting with 0xEF
+const EOF_MAGIC_PREFIX: u8 = 0xEF;
+
+/// Converts a memory offset or size taken from the stack, rejecting values
+/// no memory could ever reach.
+fn as_offset(value: U256) -> EvmResult<usize> {
+    if value > U256::from(u32::MAX) {
+        return Err(EvmError::MemoryLimit);
+    }
+    Ok(value.as_usize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        state::{Account, InMemoryDb, KECCAK_EMPTY},
+        types::AccessListItem,
+    };
+
+    const PUSH2: u8 = PUSH1 + 1;
+    const PUSH5: u8 = PUSH1 + 4;
+
+    /// PUSH32 of `value`.
+    fn push(value: U256) -> Vec<u8> {
+        let mut code = vec![PUSH32; 33];
+        value.to_big_endian(&mut code[1..]);
+        code
+    }
+
+    /// Runs `code` as a plain frame and returns the stack it leaves.
+    fn stack_after(code: &[u8]) -> Vec<U256> {
+        let mut state = JournaledState::new(InMemoryDb::new());
+        let txn = Transaction {
+            gas_limit: 1_000_000,
+            ..Transaction::default()
+        };
+        let mut evm = EVM::init(code, txn, false, &mut state);
+        evm.run().unwrap();
+        evm.stack().iter().map(|value| **value).collect()
+    }
+
+    /// Backend with a funded sender and the account the code runs as.
+    fn funded_db(storage: &[(u64, u64)]) -> InMemoryDb {
+        let txn = Transaction::default();
+        let mut db = InMemoryDb::new();
+        db.insert_account(
+            txn.caller,
+            Account {
+                balance: 1_000_000_000,
+                ..Account::default()
+            },
+        );
+        db.insert_account(
+            txn.this_addr,
+            Account {
+                balance: 10,
+                storage: storage
+                    .iter()
+                    .map(|&(slot, value)| (slot.into(), value.into()))
+                    .collect(),
+                ..Account::default()
+            },
+        );
+        db
+    }
+
+    /// Runs `code` as a whole transaction under `spec` and returns its
+    /// result with the state it left.
+    fn transact(
+        code: &[u8],
+        spec: SpecId,
+        txn: Transaction,
+        storage: &[(u64, u64)],
+    ) -> (Result<ExecutionResult, InvalidTransaction>, InMemoryDb) {
+        let mut state = JournaledState::new(funded_db(storage));
+        let mut evm = EVM::init(code, txn, false, &mut state).with_spec(spec);
+        let result = evm.transact();
+        (result, state.into_db())
+    }
+
+    /// Gas a whole transaction running `code` uses under `spec`, refund
+    /// included.
+    fn tx_gas(code: &[u8], spec: SpecId, storage: &[(u64, u64)]) -> u64 {
+        let txn = Transaction {
+            gas_limit: 1_000_000,
+            ..Transaction::default()
+        };
+        let result = transact(code, spec, txn, storage).0.unwrap();
+        assert!(result.success);
+        result.gas_used
+    }
+
+    /// Result of `op` applied to `a`, `b` and the modulus `n`.
+    fn modular(op: u8, a: U256, b: U256, n: U256) -> U256 {
+        let code = [push(n), push(b), push(a), vec![op]].concat();
+        stack_after(&code)[0]
+    }
+
+    #[test]
+    fn addmod_does_not_truncate_the_sum() {
+        let max = U256::MAX;
+        // 2^256 is 1 mod 3, a wrapped sum would give 1
+        assert_eq!(modular(ADDMOD, max, 2.into(), 3.into()), 2.into());
+        assert_eq!(modular(ADDMOD, max, max, max), U256::zero());
+        assert_eq!(modular(ADDMOD, max, max, max - 1), 2.into());
+        assert_eq!(modular(ADDMOD, max, 1.into(), U256::zero()), U256::zero());
+    }
+
+    #[test]
+    fn mulmod_does_not_truncate_the_product() {
+        let max = U256::MAX;
+        // 2^256 - 1 is 3 mod 12
+        assert_eq!(modular(MULMOD, max, max, 12.into()), 9.into());
+        assert_eq!(modular(MULMOD, max, max, max), U256::zero());
+        assert_eq!(modular(MULMOD, max, max, max - 1), U256::one());
+        assert_eq!(modular(MULMOD, max, max, U256::zero()), U256::zero());
+    }
+
+    #[test]
+    fn sload_pays_for_cold_slots() {
+        assert_eq!(
+            tx_gas(&[PUSH0, SLOAD], SpecId::Cancun, &[]),
+            21000 + 2 + 2100
+        );
+        // flat prices before Berlin, PUSH0 only exists since Shanghai
+        let code = [PUSH1, 0, SLOAD];
+        assert_eq!(tx_gas(&code, SpecId::Istanbul, &[]), 21000 + 3 + 800);
+        assert_eq!(
+            tx_gas(&code, SpecId::TangerineWhistle, &[]),
+            21000 + 3 + 200
+        );
+        assert_eq!(tx_gas(&code, SpecId::Frontier, &[]), 21000 + 3 + 50);
+
+        // the second read of the slot is warm
+        let code = [PUSH0, SLOAD, PUSH0, SLOAD];
+        assert_eq!(tx_gas(&code, SpecId::Cancun, &[]), 21000 + 4 + 2100 + 100);
+    }
+
+    #[test]
+    fn sstore_follows_eip_2200_and_2929() {
+        // PUSH1 1 PUSH0 SSTORE into an empty cold slot
+        let set = [PUSH1, 1, PUSH0, SSTORE];
+        assert_eq!(tx_gas(&set, SpecId::Cancun, &[]), 43105);
+        // after an SLOAD the slot is warm
+        let warm_set = [PUSH0, SLOAD, PUSH1, 1, PUSH0, SSTORE];
+        assert_eq!(tx_gas(&warm_set, SpecId::Cancun, &[]), 43107);
+        // changing a non-zero slot
+        let reset = [PUSH1, 2, PUSH0, SSTORE];
+        assert_eq!(tx_gas(&reset, SpecId::Cancun, &[(0, 1)]), 26005);
+        // writing the value already there
+        assert_eq!(tx_gas(&set, SpecId::Cancun, &[(0, 1)]), 23205);
+    }
+
+    #[test]
+    fn set_then_clear_refunds_up_to_a_fifth() {
+        // 0 -> 1 -> 0 in one transaction: 43209 gas, 19900 refunded but
+        // capped at 43209 / 5
+        let code = [PUSH1, 1, PUSH0, SSTORE, PUSH0, PUSH0, SSTORE];
+        assert_eq!(tx_gas(&code, SpecId::Cancun, &[]), 34568);
+    }
+
+    #[test]
+    fn memory_expansion_is_quadratic() {
+        // MSTORE at 0 takes one word
+        let code = [PUSH1, 1, PUSH0, MSTORE];
+        assert_eq!(tx_gas(&code, SpecId::Cancun, &[]), 21000 + 3 + 2 + 3 + 3);
+        // MSTORE at 0x1000 takes 129 words: 3 * 129 + 129^2 / 512
+        let code = [PUSH1, 1, PUSH2, 0x10, 0x00, MSTORE];
+        assert_eq!(tx_gas(&code, SpecId::Cancun, &[]), 21000 + 9 + 419);
+    }
+
+    #[test]
+    fn call_with_value_pays_for_the_new_account() {
+        // CALL(0, 0xdead, 1, 0, 0, 0, 0): cold access, value transfer and a
+        // new account, less the stipend the empty callee hands back
+        let code = [
+            PUSH0, PUSH0, PUSH0, PUSH0, PUSH1, 1, PUSH2, 0xde, 0xad, PUSH0, CALL,
+        ];
+        assert_eq!(
+            tx_gas(&code, SpecId::Cancun, &[]),
+            21000 + 4 * 2 + 2 * 3 + 2 + 2600 + 9000 + 25000 - 2300
+        );
+        // without value nothing is created
+        let code = [
+            PUSH0, PUSH0, PUSH0, PUSH0, PUSH0, PUSH2, 0xde, 0xad, PUSH0, CALL,
+        ];
+        assert_eq!(tx_gas(&code, SpecId::Cancun, &[]), 21000 + 6 * 2 + 3 + 2600);
+    }
+
+    /// Sends 5 wei to a contract running `code`, which calls 0xb0b. The code
+    /// at 0xb0b stores its CALLER, CALLVALUE and ADDRESS in slots 0 to 2.
+    fn call_recorder(code: &[u8]) -> (Transaction, InMemoryDb) {
+        let recorder = [
+            CALLER, PUSH0, SSTORE, CALLVALUE, PUSH1, 1, SSTORE, ADDRESS, PUSH1, 2, SSTORE,
+        ];
+        let mut db = funded_db(&[]);
+        db.insert_account(
+            0xb0b.into(),
+            Account {
+                code: recorder.to_vec(),
+                ..Account::default()
+            },
+        );
+        let txn = Transaction {
+            gas_limit: 1_000_000,
+            value: 5,
+            ..Transaction::default()
+        };
+        let mut state = JournaledState::new(db);
+        let result = EVM::init(code, txn.clone(), false, &mut state)
+            .with_spec(SpecId::Cancun)
+            .transact()
+            .unwrap();
+        assert!(result.success);
+        (txn, state.into_db())
+    }
+
+    #[test]
+    fn delegatecall_keeps_the_callers_context() {
+        // DELEGATECALL(gas, 0xb0b, 0, 0, 0, 0)
+        let code = [
+            PUSH0,
+            PUSH0,
+            PUSH0,
+            PUSH0,
+            PUSH2,
+            0x0b,
+            0x0b,
+            GAS,
+            DELEGATECALL,
+        ];
+        let (txn, db) = call_recorder(&code);
+        let stored = |address: &TransparentU256, slot: u64| db.get_storage(address, &slot.into());
+        assert_eq!(stored(&txn.this_addr, 0), *txn.caller);
+        assert_eq!(stored(&txn.this_addr, 1), 5.into());
+        assert_eq!(stored(&txn.this_addr, 2), *txn.this_addr);
+        assert_eq!(stored(&0xb0b.into(), 2), U256::zero());
+    }
+
+    #[test]
+    fn callcode_runs_the_code_as_the_caller() {
+        // CALLCODE(gas, 0xb0b, 3, 0, 0, 0, 0)
+        let code = [
+            PUSH0, PUSH0, PUSH0, PUSH0, PUSH1, 3, PUSH2, 0x0b, 0x0b, GAS, CALLCODE,
+        ];
+        let (txn, db) = call_recorder(&code);
+        let stored = |address: &TransparentU256, slot: u64| db.get_storage(address, &slot.into());
+        assert_eq!(stored(&txn.this_addr, 0), *txn.this_addr);
+        assert_eq!(stored(&txn.this_addr, 1), 3.into());
+        assert_eq!(stored(&txn.this_addr, 2), *txn.this_addr);
+        assert_eq!(stored(&0xb0b.into(), 2), U256::zero());
+        // the value goes from the contract to itself
+        assert_eq!(balance(&db, txn.this_addr), 10 + 5);
+    }
+
+    #[test]
+    fn logs_keep_their_topics_in_order() {
+        let mut code = vec![PUSH1, 0xaa, PUSH1, 0, MSTORE8];
+        for num_topics in 0..=4u8 {
+            // the first topic is popped right after the memory range
+            for topic in (1..=num_topics).rev() {
+                code.extend([PUSH1, 0x10 + topic]);
+            }
+            code.extend([PUSH1, 1, PUSH1, 0, LOG0 + num_topics]);
+        }
+        let mut state = JournaledState::new(InMemoryDb::new());
+        let txn = Transaction {
+            gas_limit: 1_000_000,
+            ..Transaction::default()
+        };
+        let this_addr = txn.this_addr.clone();
+        let mut evm = EVM::init(&code, txn, false, &mut state);
+        evm.run().unwrap();
+
+        assert_eq!(evm.logs().len(), 5);
+        for (num_topics, log) in evm.logs().iter().enumerate() {
+            let topics: Vec<U256> = (1..=num_topics as u64).map(|t| (0x10 + t).into()).collect();
+            assert_eq!(log.topics.iter().map(|t| **t).collect::<Vec<_>>(), topics);
+            assert_eq!(log.data, [0xaa]);
+            assert_eq!(log.address, this_addr);
+        }
+    }
+
+    #[test]
+    fn reverted_frames_drop_their_logs() {
+        let logs_then_reverts = [PUSH1, 0, PUSH1, 0, LOG0, PUSH1, 0, PUSH1, 0, REVERT];
+        let mut db = funded_db(&[]);
+        db.insert_account(
+            0xb0b.into(),
+            Account {
+                code: logs_then_reverts.to_vec(),
+                ..Account::default()
+            },
+        );
+        // CALL(gas, 0xb0b, 0, 0, 0, 0, 0), then LOG0 of its own
+        let code = [
+            PUSH1, 0, PUSH1, 0, PUSH1, 0, PUSH1, 0, PUSH1, 0, PUSH2, 0x0b, 0x0b, GAS, CALL, PUSH1,
+            0, PUSH1, 0, LOG0,
+        ];
+        let txn = Transaction {
+            gas_limit: 1_000_000,
+            ..Transaction::default()
+        };
+        let this_addr = txn.this_addr.clone();
+        let mut state = JournaledState::new(db);
+        let result = EVM::init(&code, txn.clone(), false, &mut state)
+            .with_spec(SpecId::Cancun)
+            .transact()
+            .unwrap();
+        assert!(result.success);
+        assert_eq!(state.logs().len(), 1);
+        assert_eq!(state.logs()[0].address, this_addr);
+
+        // a reverted transaction keeps none
+        let txn = Transaction { nonce: 1, ..txn };
+        let result = EVM::init(&logs_then_reverts, txn, false, &mut state)
+            .with_spec(SpecId::Cancun)
+            .transact()
+            .unwrap();
+        assert!(!result.success);
+        assert!(state.logs().is_empty());
+    }
+
+    #[test]
+    fn tstore_fails_a_static_call() {
+        let mut db = funded_db(&[]);
+        db.insert_account(
+            0xb0b.into(),
+            Account {
+                code: vec![PUSH1, 1, PUSH1, 0, TSTORE],
+                ..Account::default()
+            },
+        );
+        // slot 0 = STATICCALL(10000, 0xb0b, 0, 0, 0, 0) == 0, the failed call
+        // consuming only the gas it was given
+        let code = [
+            PUSH1, 0, PUSH1, 0, PUSH1, 0, PUSH1, 0, PUSH2, 0x0b, 0x0b, PUSH2, 0x27, 0x10,
+            STATICCALL, ISZERO, PUSH1, 0, SSTORE,
+        ];
+        let txn = Transaction {
+            gas_limit: 1_000_000,
+            ..Transaction::default()
+        };
+        let this_addr = txn.this_addr.clone();
+        let mut state = JournaledState::new(db);
+        let result = EVM::init(&code, txn, false, &mut state)
+            .with_spec(SpecId::Cancun)
+            .transact()
+            .unwrap();
+        assert!(result.success);
+        assert_eq!(state.get_storage(&this_addr, &0.into()), 1.into());
+    }
+
+    #[test]
+    fn transient_storage_is_cleared_after_the_transaction() {
+        // TSTORE(0, 1), then copies TLOAD(0) to slot 0
+        let stores = [
+            PUSH1, 1, PUSH1, 0, TSTORE, PUSH1, 0, TLOAD, PUSH1, 0, SSTORE,
+        ];
+        // slot 1 = TLOAD(0) == 0
+        let loads = [PUSH1, 0, TLOAD, ISZERO, PUSH1, 1, SSTORE];
+        let txn = Transaction {
+            gas_limit: 1_000_000,
+            ..Transaction::default()
+        };
+        let this_addr = txn.this_addr.clone();
+        let mut state = JournaledState::new(funded_db(&[]));
+        for (nonce, code) in [&stores[..], &loads[..]].into_iter().enumerate() {
+            let txn = Transaction {
+                nonce: nonce as u64,
+                ..txn.clone()
+            };
+            let result = EVM::init(code, txn, false, &mut state)
+                .with_spec(SpecId::Cancun)
+                .transact()
+                .unwrap();
+            assert!(result.success);
+        }
+        assert_eq!(state.get_storage(&this_addr, &0.into()), 1.into());
+        assert_eq!(state.get_storage(&this_addr, &1.into()), 1.into());
+    }
+
+    /// First word of memory after MCOPY(dst, src, 8) over the bytes 0 to 8.
+    fn memory_after_mcopy(dst: u8, src: u8) -> Vec<u8> {
+        let mut word = [0u8; 32];
+        word[..9].copy_from_slice(&[0, 1, 2, 3, 4, 5, 6, 7, 8]);
+        let code = [
+            push(U256::from_big_endian(&word)),
+            vec![PUSH1, 0, MSTORE, PUSH1, 8, PUSH1, src, PUSH1, dst, MCOPY],
+        ]
+        .concat();
+        let mut state = JournaledState::new(InMemoryDb::new());
+        let txn = Transaction {
+            gas_limit: 1_000_000,
+            ..Transaction::default()
+        };
+        let mut evm = EVM::init(&code, txn, false, &mut state).with_spec(SpecId::Cancun);
+        evm.run().unwrap();
+        evm.memory()[..32].to_vec()
+    }
+
+    #[test]
+    fn mcopy_handles_overlapping_ranges() {
+        // the EIP-5656 examples, copying backwards and forwards by one byte
+        let expected = [1, 2, 3, 4, 5, 6, 7, 8, 8];
+        assert_eq!(memory_after_mcopy(0, 1)[..9], expected);
+        assert!(memory_after_mcopy(0, 1)[9..].iter().all(|b| *b == 0));
+        let expected = [0, 0, 1, 2, 3, 4, 5, 6, 7];
+        assert_eq!(memory_after_mcopy(1, 0)[..9], expected);
+        assert_eq!(memory_after_mcopy(0, 0)[..9], [0, 1, 2, 3, 4, 5, 6, 7, 8]);
+    }
+
+    fn balance(db: &InMemoryDb, address: impl Into<TransparentU256>) -> u64 {
+        db.get_account_info(&address.into())
+            .map_or(0, |a| a.balance)
+    }
+
+    #[test]
+    fn settlement_pays_the_priority_fee_to_the_coinbase() {
+        let txn = Transaction {
+            gas_limit: 100_000,
+            gas_price: 10,
+            ..Transaction::default()
+        };
+        let code = [PUSH1, 1, PUSH0, SSTORE, PUSH0, PUSH0, SSTORE];
+        let (result, db) = transact(&code, SpecId::Cancun, txn.clone(), &[]);
+        assert_eq!(result.unwrap().gas_used, 34568);
+        // only the gas used after the refund is paid for, the base fee of 1
+        // is burnt
+        assert_eq!(balance(&db, txn.caller.clone()), 1_000_000_000 - 345680);
+        assert_eq!(balance(&db, Block::default().coinbase), 34568 * 9);
+        assert_eq!(db.get_account_info(&txn.caller).unwrap().nonce, 1);
+    }
+
+    #[test]
+    fn settlement_before_london_pays_the_whole_price() {
+        let txn = Transaction {
+            gas_limit: 100_000,
+            gas_price: 10,
+            ..Transaction::default()
+        };
+        let (result, db) = transact(&[PUSH1, 0, SLOAD], SpecId::Berlin, txn.clone(), &[]);
+        assert_eq!(result.unwrap().gas_used, 23103);
+        assert_eq!(balance(&db, txn.caller), 1_000_000_000 - 231030);
+        assert_eq!(balance(&db, Block::default().coinbase), 231030);
+    }
+
+    #[test]
+    fn reverted_transaction_still_pays_its_gas() {
+        let txn = Transaction {
+            gas_limit: 100_000,
+            gas_price: 10,
+            value: 5,
+            ..Transaction::default()
+        };
+        let code = [PUSH1, 1, PUSH0, SSTORE, PUSH0, PUSH0, REVERT];
+        let (result, db) = transact(&code, SpecId::Cancun, txn.clone(), &[]);
+        let result = result.unwrap();
+        assert!(!result.success);
+        // no refund for the reverted write, and the value stays with the sender
+        assert_eq!(result.gas_used, 43109);
+        assert_eq!(balance(&db, txn.caller.clone()), 1_000_000_000 - 431090);
+        assert_eq!(balance(&db, txn.this_addr.clone()), 10);
+        assert_eq!(db.get_storage(&txn.this_addr, &U256::zero()), U256::zero());
+        assert_eq!(db.get_account_info(&txn.caller).unwrap().nonce, 1);
+    }
+
+    #[test]
+    fn halted_transaction_is_charged_and_reports_why() {
+        let txn = Transaction {
+            gas_limit: 100_000,
+            gas_price: 10,
+            ..Transaction::default()
+        };
+        let (result, db) = transact(&[INVALID], SpecId::Cancun, txn.clone(), &[]);
+        let result = result.unwrap();
+        assert!(!result.success);
+        assert_eq!(result.halt_reason, Some(EvmError::InvalidOpcode(INVALID)));
+        assert_eq!(result.gas_used, 100_000);
+        let sender = db.get_account_info(&txn.caller).unwrap();
+        assert_eq!(
+            (sender.balance, sender.nonce),
+            (1_000_000_000 - 1_000_000, 1)
+        );
+    }
+
+    #[test]
+    fn credits_saturate_at_the_maximum_balance() {
+        let txn = Transaction {
+            gas_price: 10,
+            value: 5,
+            ..Transaction::default()
+        };
+        let coinbase: TransparentU256 = Block::default().coinbase.into();
+        let mut db = funded_db(&[]);
+        for address in [&coinbase, &txn.this_addr] {
+            let rich = Account {
+                balance: u64::MAX - 1,
+                ..Account::default()
+            };
+            db.insert_account(address.clone(), rich);
+        }
+        let mut state = JournaledState::new(db);
+        let mut evm = EVM::init(&[], txn.clone(), false, &mut state);
+        assert!(evm.transact().unwrap().success);
+        let db = state.into_db();
+        assert_eq!(balance(&db, txn.this_addr), u64::MAX);
+        assert_eq!(balance(&db, coinbase), u64::MAX);
+    }
+
+    #[test]
+    fn origin_is_the_sender() {
+        let txn = Transaction {
+            gas_limit: 100_000,
+            ..Transaction::default()
+        };
+        let code = [ORIGIN, PUSH0, MSTORE, PUSH1, 32, PUSH0, RETURN];
+        let (result, _) = transact(&code, SpecId::Cancun, txn.clone(), &[]);
+        let caller: U256 = *txn.caller;
+        assert_eq!(U256::from_big_endian(&result.unwrap().return_data), caller);
+    }
+
+    /// Intrinsic gas of `txn` running `code`, or why it is rejected.
+    fn validate(code: &[u8], spec: SpecId, txn: Transaction) -> Result<u64, InvalidTransaction> {
+        let mut state = JournaledState::new(funded_db(&[]));
+        EVM::init(code, txn, false, &mut state)
+            .with_spec(spec)
+            .validate_transaction()
+    }
+
+    #[test]
+    fn intrinsic_gas_counts_calldata_creation_and_access_list() {
+        let call = Transaction {
+            gas_limit: 100_000,
+            data: vec![0, 1],
+            ..Transaction::default()
+        };
+        assert_eq!(
+            validate(&[], SpecId::Cancun, call.clone()),
+            Ok(21000 + 4 + 16)
+        );
+        assert_eq!(validate(&[], SpecId::Frontier, call), Ok(21000 + 4 + 68));
+
+        // the init code is the payload, 2 words of it since Shanghai
+        let create = Transaction {
+            gas_limit: 100_000,
+            is_create: true,
+            ..Transaction::default()
+        };
+        let init_code = [1; 64];
+        assert_eq!(
+            validate(&init_code, SpecId::Cancun, create.clone()),
+            Ok(21000 + 32000 + 64 * 16 + 2 * 2)
+        );
+        assert_eq!(
+            validate(&init_code, SpecId::Merge, create.clone()),
+            Ok(21000 + 32000 + 64 * 16)
+        );
+        assert_eq!(
+            validate(&init_code, SpecId::Frontier, create),
+            Ok(21000 + 64 * 68)
+        );
+
+        let with_access_list = Transaction {
+            gas_limit: 100_000,
+            access_list: vec![AccessListItem {
+                address: 0xdead.into(),
+                storage_keys: vec![U256::zero(), U256::one()],
+            }],
+            ..Transaction::default()
+        };
+        assert_eq!(
+            validate(&[], SpecId::Cancun, with_access_list),
+            Ok(21000 + 2400 + 2 * 1900)
+        );
+    }
+
+    #[test]
+    fn rejects_a_gas_limit_below_the_intrinsic_gas() {
+        let txn = Transaction {
+            gas_limit: 21019,
+            data: vec![0, 1],
+            ..Transaction::default()
+        };
+        assert_eq!(
+            validate(&[], SpecId::Cancun, txn),
+            Err(InvalidTransaction::IntrinsicGasTooLow {
+                gas_limit: 21019,
+                intrinsic_gas: 21020,
+            })
+        );
+
+        // in Prague the limit has to cover the calldata floor too
+        let txn = Transaction {
+            gas_limit: 21039,
+            data: vec![1],
+            ..Transaction::default()
+        };
+        assert_eq!(validate(&[], SpecId::Cancun, txn.clone()), Ok(21016));
+        assert_eq!(
+            validate(&[], SpecId::Prague, txn),
+            Err(InvalidTransaction::IntrinsicGasTooLow {
+                gas_limit: 21039,
+                intrinsic_gas: 21040,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_transactions_the_block_or_sender_cannot_cover() {
+        let base = Transaction {
+            gas_limit: 100_000,
+            ..Transaction::default()
+        };
+        let over_block = Transaction {
+            gas_limit: 30_000_001,
+            ..base.clone()
+        };
+        assert_eq!(
+            validate(&[], SpecId::Cancun, over_block),
+            Err(InvalidTransaction::GasLimitExceedsBlock {
+                gas_limit: 30_000_001,
+                block_gas_limit: 30_000_000,
+            })
+        );
+
+        let below_basefee = Transaction {
+            gas_price: 0,
+            ..base.clone()
+        };
+        assert_eq!(
+            validate(&[], SpecId::Cancun, below_basefee.clone()),
+            Err(InvalidTransaction::GasPriceBelowBaseFee {
+                gas_price: 0,
+                basefee: 1,
+            })
+        );
+        assert_eq!(validate(&[], SpecId::Berlin, below_basefee), Ok(21000));
+
+        let wrong_nonce = Transaction {
+            nonce: 1,
+            ..base.clone()
+        };
+        assert_eq!(
+            validate(&[], SpecId::Cancun, wrong_nonce),
+            Err(InvalidTransaction::NonceMismatch {
+                expected: 0,
+                got: 1,
+            })
+        );
+
+        // 100000 gas at 10000 each plus the value is one more than the balance
+        let too_expensive = Transaction {
+            gas_price: 10_000,
+            value: 1,
+            ..base
+        };
+        assert_eq!(
+            validate(&[], SpecId::Cancun, too_expensive),
+            Err(InvalidTransaction::InsufficientFunds {
+                balance: 1_000_000_000,
+                cost: 1_000_000_001,
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_init_code_over_the_limit_since_shanghai() {
+        let create = Transaction {
+            gas_limit: 10_000_000,
+            is_create: true,
+            ..Transaction::default()
+        };
+        let init_code = vec![0; 2 * MAX_CODE_SIZE + 1];
+        assert_eq!(
+            validate(&init_code, SpecId::Shanghai, create.clone()),
+            Err(InvalidTransaction::InitcodeTooLarge(2 * MAX_CODE_SIZE + 1))
+        );
+        assert!(validate(&init_code, SpecId::Merge, create).is_ok());
+    }
+
+    #[test]
+    fn create_halts_on_init_code_over_the_limit_since_shanghai() {
+        // CREATE(0, 0, 0xc001)
+        let code = [PUSH2, 0xc0, 0x01, PUSH1, 0, PUSH1, 0, CREATE];
+        let run = |spec| {
+            let mut state = JournaledState::new(funded_db(&[]));
+            let txn = Transaction {
+                gas_limit: 1_000_000,
+                ..Transaction::default()
+            };
+            EVM::init(&code, txn, false, &mut state)
+                .with_spec(spec)
+                .run()
+                .map(|result| result.success)
+        };
+        assert_eq!(
+            run(SpecId::Shanghai),
+            Err(EvmError::InitcodeTooLarge(0xc001))
+        );
+        assert_eq!(run(SpecId::Merge), Ok(true));
+    }
+
+    /// Code that writes `init_code` to memory, runs it with `op` and stores
+    /// the address pushed in `slot`. CREATE2 uses salt 7.
+    fn create_and_store(op: u8, init_code: &[u8], slot: u8) -> Vec<u8> {
+        let length = init_code.len() as u8;
+        let mut code = [
+            push(U256::from_big_endian(init_code)),
+            vec![PUSH1, 0, MSTORE],
+        ]
+        .concat();
+        if op == CREATE2 {
+            code.extend([PUSH1, 7]);
+        }
+        code.extend([PUSH1, length, PUSH1, 32 - length, PUSH1, 0, op]);
+        code.extend([PUSH1, slot, SSTORE]);
+        code
+    }
+
+    #[test]
+    fn create_deploys_the_returned_code() {
+        // returns the single byte 0x5f
+        let init_code = [PUSH1, 0x5f, PUSH1, 0, MSTORE8, PUSH1, 1, PUSH1, 0, RETURN];
+        let reverting = [PUSH1, 0, PUSH1, 0, REVERT];
+        let code = [
+            create_and_store(CREATE, &init_code, 0),
+            create_and_store(CREATE2, &init_code, 1),
+            create_and_store(CREATE, &reverting, 2),
+            // same salt and init code, so the same address again. A collision
+            // consumes the gas given to the creation, so it goes last.
+            create_and_store(CREATE2, &init_code, 3),
+        ]
+        .concat();
+        let txn = Transaction {
+            gas_limit: 1_000_000,
+            ..Transaction::default()
+        };
+        let this_addr = txn.this_addr.clone();
+        let (result, db) = transact(&code, SpecId::Cancun, txn, &[]);
+        assert!(result.unwrap().success);
+
+        let created = create_address(&this_addr, 0);
+        let created2 = create2_address(&this_addr, &7.into(), &init_code);
+        let stored = |slot: u64| db.get_storage(&this_addr, &slot.into());
+        assert_eq!(stored(0), *created);
+        assert_eq!(stored(1), *created2);
+        assert_eq!(stored(2), U256::zero());
+        assert_eq!(stored(3), U256::zero());
+
+        let info = db.get_account_info(&created).unwrap();
+        assert_eq!(info.nonce, 1);
+        assert_eq!(db.get_code_by_hash(&info.code_hash), Some(vec![0x5f]));
+        // failed creations still bump the creator's nonce
+        assert_eq!(db.get_account_info(&this_addr).unwrap().nonce, 4);
+        assert!(db
+            .get_account_info(&create_address(&this_addr, 2))
+            .is_none());
+    }
+
+    #[test]
+    fn rejected_transaction_leaves_the_state_alone() {
+        let txn = Transaction {
+            gas_limit: 20_000,
+            ..Transaction::default()
+        };
+        let (result, db) = transact(&[PUSH0, PUSH0, SSTORE], SpecId::Cancun, txn.clone(), &[]);
+        assert!(matches!(
+            result,
+            Err(InvalidTransaction::IntrinsicGasTooLow { .. })
+        ));
+        let sender = db.get_account_info(&txn.caller).unwrap();
+        assert_eq!((sender.balance, sender.nonce), (1_000_000_000, 0));
+        assert_eq!(balance(&db, Block::default().coinbase), 0);
+    }
+
+    /// Versioned hash with the KZG version byte and `n` as the rest.
+    fn blob_hash(n: u64) -> U256 {
+        (U256::from(VERSIONED_HASH_VERSION_KZG) << 248) | U256::from(n)
+    }
+
+    #[test]
+    fn blobhash_reads_zero_past_the_last_blob() {
+        let code = [
+            vec![PUSH1, 0, BLOBHASH, PUSH1, 1, BLOBHASH, PUSH1, 2, BLOBHASH],
+            push(U256::MAX),
+            vec![BLOBHASH],
+        ]
+        .concat();
+        let mut state = JournaledState::new(InMemoryDb::new());
+        let txn = Transaction {
+            gas_limit: 1_000_000,
+            blob_versioned_hashes: vec![blob_hash(1), blob_hash(2)],
+            ..Transaction::default()
+        };
+        let mut evm = EVM::init(&code, txn, false, &mut state).with_spec(SpecId::Cancun);
+        evm.run().unwrap();
+        let stack: Vec<U256> = evm.stack().iter().map(|value| **value).collect();
+        assert_eq!(
+            stack,
+            [blob_hash(1), blob_hash(2), U256::zero(), U256::zero()]
+        );
+    }
+
+    #[test]
+    fn rejects_blobs_the_block_or_sender_cannot_cover() {
+        let base = Transaction {
+            gas_limit: 100_000,
+            blob_versioned_hashes: vec![blob_hash(1)],
+            max_fee_per_blob_gas: 1,
+            ..Transaction::default()
+        };
+        assert_eq!(validate(&[], SpecId::Cancun, base.clone()), Ok(21000));
+
+        let wrong_version = Transaction {
+            blob_versioned_hashes: vec![blob_hash(1), U256::from(2) << 248],
+            ..base.clone()
+        };
+        assert_eq!(
+            validate(&[], SpecId::Cancun, wrong_version),
+            Err(InvalidTransaction::InvalidBlobVersionedHash(
+                U256::from(2) << 248
+            ))
+        );
+
+        let below_blob_basefee = Transaction {
+            max_fee_per_blob_gas: 0,
+            ..base.clone()
+        };
+        assert_eq!(
+            validate(&[], SpecId::Cancun, below_blob_basefee.clone()),
+            Err(InvalidTransaction::BlobFeeBelowBaseFee {
+                max_fee: 0,
+                blob_basefee: 1.into(),
+            })
+        );
+        // blobs mean nothing before Cancun
+        assert_eq!(
+            validate(&[], SpecId::Shanghai, below_blob_basefee),
+            Ok(21000)
+        );
+
+        // the balance has to cover the blob gas at the maximum fee offered
+        let too_expensive = Transaction {
+            max_fee_per_blob_gas: 7700,
+            ..base
+        };
+        assert_eq!(
+            validate(&[], SpecId::Cancun, too_expensive),
+            Err(InvalidTransaction::InsufficientFunds {
+                balance: 1_000_000_000,
+                cost: 100_000 + gas::GAS_PER_BLOB * 7700,
+            })
+        );
+    }
+
+    #[test]
+    fn blob_free_transaction_ignores_a_huge_blob_base_fee() {
+        let mut state = JournaledState::new(funded_db(&[]));
+        let block = Block {
+            excess_blob_gas: 200_000_000,
+            ..Block::default()
+        };
+        let mut evm = EVM::init(&[], Transaction::default(), false, &mut state).with_block(block);
+        assert_eq!(evm.transact().unwrap().gas_used, 21000);
+    }
+
+    #[test]
+    fn create_without_gas_for_the_deposit_leaves_no_code_before_homestead() {
+        // MSTORE the init code PUSH1 100 PUSH1 0 RETURN, then CREATE(0, 27, 5)
+        let code = [
+            PUSH5, 0x60, 0x64, 0x60, 0x00, 0xf3, PUSH1, 0, MSTORE, PUSH1, 5, PUSH1, 27, PUSH1, 0,
+            CREATE,
+        ];
+        // enough for the init code to run, far from the 20000 deposit
+        let run = |spec| {
+            let mut state = JournaledState::new(funded_db(&[]));
+            let txn = Transaction {
+                gas_limit: 12 + 9 + gas::CREATE_COST + 118,
+                ..Transaction::default()
+            };
+            let mut evm = EVM::init(&code, txn, false, &mut state).with_spec(spec);
+            evm.run().unwrap();
+            let created = *evm.stack()[0];
+            let gas_used = evm.gas_used();
+            let code_hash = state
+                .get_account_info(&created.into())
+                .map(|info| info.code_hash);
+            (created, gas_used, code_hash)
+        };
+
+        let (created, gas_used, code_hash) = run(SpecId::Frontier);
+        assert!(!created.is_zero());
+        assert_eq!(gas_used, 12 + 9 + gas::CREATE_COST + 18);
+        assert_eq!(code_hash, Some(KECCAK_EMPTY));
+
+        let (created, gas_used, _) = run(SpecId::Homestead);
+        assert!(created.is_zero());
+        assert_eq!(gas_used, 12 + 9 + gas::CREATE_COST + 118);
+    }
+
+    #[test]
+    fn create_transaction_without_gas_for_the_deposit_before_homestead() {
+        // PUSH1 100 PUSH1 0 RETURN, 4 non-zero bytes and a zero one
+        let init_code = [PUSH1, 100, PUSH1, 0, RETURN];
+        let run = |spec, intrinsic_gas: u64| {
+            let txn = Transaction {
+                gas_limit: intrinsic_gas + 118,
+                is_create: true,
+                ..Transaction::default()
+            };
+            let created = create_address(&txn.caller, 0);
+            let (result, db) = transact(&init_code, spec, txn, &[]);
+            (result.unwrap(), db.get_account_info(&created))
+        };
+
+        let (result, created) = run(SpecId::Frontier, 21000 + 4 * 68 + 4);
+        assert!(result.success);
+        assert_eq!(result.gas_used, 21000 + 4 * 68 + 4 + 18);
+        assert_eq!(created.map(|info| info.code_hash), Some(KECCAK_EMPTY));
+
+        let (result, created) = run(SpecId::Homestead, 53000 + 4 * 68 + 4);
+        assert!(!result.success);
+        assert_eq!(result.halt_reason, Some(EvmError::OutOfGas));
+        assert!(created.is_none());
+    }
+
+    /// Gas the callee at 0xb0b sees when a frame given 100_000 under `spec`
+    /// calls it asking for `requested`.
+    fn gas_in_callee(spec: SpecId, requested: U256) -> EvmResult<U256> {
+        let callee = [GAS, PUSH1, 0, MSTORE, PUSH1, 32, PUSH1, 0, RETURN];
+        let mut db = funded_db(&[]);
+        db.insert_account(
+            0xb0b.into(),
+            Account {
+                code: callee.to_vec(),
+                ..Account::default()
+            },
+        );
+        // CALL(requested, 0xb0b, 0, 0, 0, 0, 32)
+        let code = [
+            vec![
+                PUSH1, 32, PUSH1, 0, PUSH1, 0, PUSH1, 0, PUSH1, 0, PUSH2, 0x0b, 0x0b,
+            ],
+            push(requested),
+            vec![CALL],
+        ]
+        .concat();
+        let txn = Transaction {
+            gas_limit: 100_000,
+            ..Transaction::default()
+        };
+        let mut state = JournaledState::new(db);
+        let mut evm = EVM::init(&code, txn, false, &mut state).with_spec(spec);
+        evm.run()?;
+        Ok(U256::from_big_endian(&evm.memory()[..32]))
+    }
+
+    #[test]
+    fn calls_forward_all_but_one_64th_since_eip_150() {
+        // left once the pushes, the cold CALL and the output memory are paid
+        let left: u64 = 100_000 - 7 * 3 - 2600 - 3;
+        // the callee's own GAS costs 2
+        let expected = left - left / 64 - 2;
+        assert_eq!(
+            gas_in_callee(SpecId::Cancun, U256::MAX),
+            Ok(expected.into())
+        );
+        assert_eq!(gas_in_callee(SpecId::Cancun, 5000.into()), Ok(4998.into()));
+    }
+
+    #[test]
+    fn calls_asking_for_more_than_is_left_run_out_of_gas_before_eip_150() {
+        let left: u64 = 100_000 - 7 * 3 - 40 - 3;
+        assert_eq!(
+            gas_in_callee(SpecId::Homestead, left.into()),
+            Ok((left - 2).into())
+        );
+        assert_eq!(
+            gas_in_callee(SpecId::Homestead, (left + 1).into()),
+            Err(EvmError::OutOfGas)
+        );
+    }
+
+    #[test]
+    fn calls_nest_down_to_the_depth_limit() {
+        // counts its frames in slot 0, then calls itself with all the gas
+        // Homestead lets it forward
+        let code = [
+            vec![PUSH1, 0, SLOAD, PUSH1, 1, ADD, PUSH1, 0, SSTORE],
+            [PUSH1, 0].repeat(5),
+            vec![ADDRESS, PUSH1, 0xff, GAS, SUB, CALL],
+        ]
+        .concat();
+        let txn = Transaction {
+            gas_limit: 30_000_000,
+            ..Transaction::default()
+        };
+        let mut db = funded_db(&[]);
+        db.set_code(&txn.this_addr, code.clone());
+        let this_addr = txn.this_addr.clone();
+        let mut state = JournaledState::new(db);
+        let result = EVM::init(&code, txn, false, &mut state)
+            .with_spec(SpecId::Homestead)
+            .transact()
+            .unwrap();
+        assert!(result.success);
+        // the outermost frame and one per level of nesting
+        let db = state.into_db();
+        assert_eq!(db.get_storage(&this_addr, &0.into()), 1025.into());
+    }
+
+    #[test]
+    fn selfdestruct_moves_the_balance_and_only_deletes_new_contracts() {
+        let code = [PUSH2, 0xbe, 0xef, SELFDESTRUCT];
+        let txn = Transaction {
+            gas_limit: 1_000_000,
+            ..Transaction::default()
+        };
+        let this_addr = txn.this_addr.clone();
+        let (result, db) = transact(&code, SpecId::Cancun, txn.clone(), &[]);
+        assert!(result.unwrap().success);
+        assert_eq!(balance(&db, 0xbeef), 10);
+        // EIP-6780: a contract from an earlier transaction survives, emptied
+        assert_eq!(db.get_account_info(&this_addr).map(|a| a.balance), Some(0));
+
+        let (_, db) = transact(&code, SpecId::Shanghai, txn, &[]);
+        assert_eq!(balance(&db, 0xbeef), 10);
+        assert!(db.get_account_info(&this_addr).is_none());
+
+        // created by this very transaction, so deleted even under Cancun
+        let txn = Transaction {
+            gas_limit: 1_000_000,
+            value: 7,
+            is_create: true,
+            ..Transaction::default()
+        };
+        let created = create_address(&txn.caller, 0);
+        let (result, db) = transact(&code, SpecId::Cancun, txn, &[]);
+        assert!(result.unwrap().success);
+        assert_eq!(balance(&db, 0xbeef), 7);
+        assert!(db.get_account_info(&created).is_none());
+    }
+
+    #[test]
+    fn selfdestruct_to_itself_burns_only_a_new_contracts_balance() {
+        let code = [ADDRESS, SELFDESTRUCT];
+        let txn = Transaction {
+            gas_limit: 1_000_000,
+            ..Transaction::default()
+        };
+        let this_addr = txn.this_addr.clone();
+        let (_, db) = transact(&code, SpecId::Cancun, txn, &[]);
+        assert_eq!(balance(&db, this_addr), 10);
+
+        let txn = Transaction {
+            gas_limit: 1_000_000,
+            value: 7,
+            is_create: true,
+            ..Transaction::default()
+        };
+        let caller = txn.caller.clone();
+        let created = create_address(&caller, 0);
+        let (result, db) = transact(&code, SpecId::Cancun, txn, &[]);
+        let gas_used = result.unwrap().gas_used;
+        assert!(db.get_account_info(&created).is_none());
+        // the 7 wei are gone rather than back with the sender
+        assert_eq!(balance(&db, caller), 1_000_000_000 - gas_used - 7);
+    }
+
+    #[test]
+    fn selfdestruct_is_rejected_in_a_static_context() {
+        let mut state = JournaledState::new(funded_db(&[]));
+        let txn = Transaction {
+            gas_limit: 1_000_000,
+            ..Transaction::default()
+        };
+        let result = EVM::init(&[CALLER, SELFDESTRUCT], txn, true, &mut state).run();
+        assert_eq!(result.unwrap_err(), EvmError::StaticStateChange);
+    }
+
+    #[test]
+    fn selfdestruct_refunds_before_london() {
+        // sends the balance to the warm sender, so no surcharge applies
+        let code = [CALLER, SELFDESTRUCT];
+        let gas = 21000 + 2 + 5000;
+        // the refund is capped at half the gas used
+        assert_eq!(tx_gas(&code, SpecId::Berlin, &[]), gas - gas / 2);
+        assert_eq!(tx_gas(&code, SpecId::London, &[]), gas);
+    }
+}
@@ -0,0 +1,524 @@
+use std::collections::{HashMap, HashSet};
+
+use primitive_types::U256;
+
+use crate::{
+    state::{Account, AccountInfo, StateDb},
+    types::{EVMLog, TransparentU256},
+};
+
+/// A single state change, holding what is needed to undo it.
+#[derive(Debug, Clone)]
+pub enum JournalEntry {
+    AccountCreated {
+        address: TransparentU256,
+    },
+    BalanceChanged {
+        address: TransparentU256,
+        previous: u64,
+    },
+    NonceChanged {
+        address: TransparentU256,
+        previous: u64,
+    },
+    StorageChanged {
+        address: TransparentU256,
+        slot: U256,
+        previous: U256,
+    },
+    CodeChanged {
+        address: TransparentU256,
+        previous: Vec<u8>,
+    },
+    LogEmitted,
+    SelfDestructed {
+        address: TransparentU256,
+    },
+    AccountWarmed {
+        address: TransparentU256,
+    },
+    StorageWarmed {
+        address: TransparentU256,
+        slot: U256,
+    },
+    RefundChanged {
+        previous: i64,
+    },
+    ContractCreated {
+        address: TransparentU256,
+    },
+    TransientStorageChanged {
+        address: TransparentU256,
+        slot: U256,
+        previous: U256,
+    },
+}
+
+/// Position in the journal to roll back to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint {
+    journal_len: usize,
+}
+
+/// World state as seen by a running transaction. Every change goes through
+/// here and is recorded, so a failed call frame can be undone with
+/// `revert_to` while the rest of the transaction keeps its changes.
+#[derive(Debug, Default)]
+pub struct JournaledState<DB> {
+    db: DB,
+    journal: Vec<JournalEntry>,
+    logs: Vec<EVMLog>,
+    selfdestructed: HashSet<TransparentU256>,
+    // contracts deployed by the current transaction, the only ones
+    // SELFDESTRUCT may still delete since EIP-6780
+    created: HashSet<TransparentU256>,
+    // slot values as they were when the transaction started
+    original_storage: HashMap<(TransparentU256, U256), U256>,
+    // EIP-2929 accessed_addresses and accessed_storage_keys
+    warm_accounts: HashSet<TransparentU256>,
+    warm_slots: HashSet<(TransparentU256, U256)>,
+    // may dip below zero mid-transaction, see EIP-2200
+    refund: i64,
+    // EIP-1153 storage that only lives for the current transaction
+    transient_storage: HashMap<(TransparentU256, U256), U256>,
+}
+
+impl<DB: StateDb> JournaledState<DB> {
+    pub fn new(db: DB) -> Self {
+        Self {
+            db,
+            journal: Vec::new(),
+            logs: Vec::new(),
+            selfdestructed: HashSet::new(),
+            created: HashSet::new(),
+            original_storage: HashMap::new(),
+            warm_accounts: HashSet::new(),
+            warm_slots: HashSet::new(),
+            refund: 0,
+            transient_storage: HashMap::new(),
+        }
+    }
+
+    pub fn db(&self) -> &DB {
+        &self.db
+    }
+
+    pub fn into_db(self) -> DB {
+        self.db
+    }
+
+    pub fn logs(&self) -> &[EVMLog] {
+        &self.logs
+    }
+
+    /// Accounts that ran SELFDESTRUCT in the current transaction and will be
+    /// deleted when it commits.
+    pub fn selfdestructed(&self) -> &HashSet<TransparentU256> {
+        &self.selfdestructed
+    }
+
+    pub fn get_account_info(&self, address: &TransparentU256) -> Option<AccountInfo> {
+        self.db.get_account_info(address)
+    }
+
+    /// Code deployed at `address`, empty for an account without any.
+    pub fn get_code(&self, address: &TransparentU256) -> Vec<u8> {
+        match self.db.get_account_info(address) {
+            Some(info) if info.has_code() => self
+                .db
+                .get_code_by_hash(&info.code_hash)
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn get_storage(&self, address: &TransparentU256, slot: &U256) -> U256 {
+        self.db.get_storage(address, slot)
+    }
+
+    /// Value of the slot before the current transaction wrote to it.
+    pub fn original_storage(&self, address: &TransparentU256, slot: &U256) -> U256 {
+        match self.original_storage.get(&(address.clone(), *slot)) {
+            Some(original) => *original,
+            None => self.db.get_storage(address, slot),
+        }
+    }
+
+    pub fn set_balance(&mut self, address: &TransparentU256, balance: u64) {
+        let previous = self.touch(address).balance;
+        self.journal.push(JournalEntry::BalanceChanged {
+            address: address.clone(),
+            previous,
+        });
+        self.db.set_balance(address, balance);
+    }
+
+    pub fn set_nonce(&mut self, address: &TransparentU256, nonce: u64) {
+        let previous = self.touch(address).nonce;
+        self.journal.push(JournalEntry::NonceChanged {
+            address: address.clone(),
+            previous,
+        });
+        self.db.set_nonce(address, nonce);
+    }
+
+    pub fn set_code(&mut self, address: &TransparentU256, code: Vec<u8>) {
+        self.touch(address);
+        let previous = self.get_code(address);
+        self.journal.push(JournalEntry::CodeChanged {
+            address: address.clone(),
+            previous,
+        });
+        self.db.set_code(address, code);
+    }
+
+    pub fn set_storage(&mut self, address: &TransparentU256, slot: U256, value: U256) {
+        self.touch(address);
+        let previous = self.db.get_storage(address, &slot);
+        self.original_storage
+            .entry((address.clone(), slot))
+            .or_insert(previous);
+        self.journal.push(JournalEntry::StorageChanged {
+            address: address.clone(),
+            slot,
+            previous,
+        });
+        self.db.set_storage(address, slot, value);
+    }
+
+    pub fn get_transient_storage(&self, address: &TransparentU256, slot: &U256) -> U256 {
+        self.transient_storage
+            .get(&(address.clone(), *slot))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn set_transient_storage(&mut self, address: &TransparentU256, slot: U256, value: U256) {
+        let key = (address.clone(), slot);
+        let previous = if value.is_zero() {
+            self.transient_storage.remove(&key)
+        } else {
+            self.transient_storage.insert(key, value)
+        };
+        self.journal.push(JournalEntry::TransientStorageChanged {
+            address: address.clone(),
+            slot,
+            previous: previous.unwrap_or_default(),
+        });
+    }
+
+    pub fn log(&mut self, log: EVMLog) {
+        self.journal.push(JournalEntry::LogEmitted);
+        self.logs.push(log);
+    }
+
+    pub fn is_created(&self, address: &TransparentU256) -> bool {
+        self.created.contains(address)
+    }
+
+    pub fn mark_created(&mut self, address: &TransparentU256) {
+        if self.created.insert(address.clone()) {
+            self.journal.push(JournalEntry::ContractCreated {
+                address: address.clone(),
+            });
+        }
+    }
+
+    /// Marks the account for deletion, returning whether it was not marked
+    /// already.
+    pub fn selfdestruct(&mut self, address: &TransparentU256) -> bool {
+        let first = self.selfdestructed.insert(address.clone());
+        if first {
+            self.journal.push(JournalEntry::SelfDestructed {
+                address: address.clone(),
+            });
+        }
+        first
+    }
+
+    /// Marks the account as accessed, returning whether it was cold before.
+    pub fn access_account(&mut self, address: &TransparentU256) -> bool {
+        let cold = self.warm_accounts.insert(address.clone());
+        if cold {
+            self.journal.push(JournalEntry::AccountWarmed {
+                address: address.clone(),
+            });
+        }
+        cold
+    }
+
+    /// Marks the slot as accessed, returning whether it was cold before.
+    pub fn access_storage(&mut self, address: &TransparentU256, slot: U256) -> bool {
+        let cold = self.warm_slots.insert((address.clone(), slot));
+        if cold {
+            self.journal.push(JournalEntry::StorageWarmed {
+                address: address.clone(),
+                slot,
+            });
+        }
+        cold
+    }
+
+    pub fn refund(&self) -> i64 {
+        self.refund
+    }
+
+    pub fn add_refund(&mut self, delta: i64) {
+        self.journal.push(JournalEntry::RefundChanged {
+            previous: self.refund,
+        });
+        self.refund += delta;
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            journal_len: self.journal.len(),
+        }
+    }
+
+    /// Undoes every change recorded after `checkpoint`, newest first.
+    pub fn revert_to(&mut self, checkpoint: Checkpoint) {
+        let undone = self.journal.split_off(checkpoint.journal_len);
+        for entry in undone.into_iter().rev() {
+            match entry {
+                JournalEntry::AccountCreated { address } => self.db.remove_account(&address),
+                JournalEntry::BalanceChanged { address, previous } => {
+                    self.db.set_balance(&address, previous)
+                }
+                JournalEntry::NonceChanged { address, previous } => {
+                    self.db.set_nonce(&address, previous)
+                }
+                JournalEntry::StorageChanged {
+                    address,
+                    slot,
+                    previous,
+                } => self.db.set_storage(&address, slot, previous),
+                JournalEntry::CodeChanged { address, previous } => {
+                    self.db.set_code(&address, previous)
+                }
+                JournalEntry::LogEmitted => {
+                    self.logs.pop();
+                }
+                JournalEntry::SelfDestructed { address } => {
+                    self.selfdestructed.remove(&address);
+                }
+                JournalEntry::AccountWarmed { address } => {
+                    self.warm_accounts.remove(&address);
+                }
+                JournalEntry::StorageWarmed { address, slot } => {
+                    self.warm_slots.remove(&(address, slot));
+                }
+                JournalEntry::RefundChanged { previous } => self.refund = previous,
+                JournalEntry::ContractCreated { address } => {
+                    self.created.remove(&address);
+                }
+                JournalEntry::TransientStorageChanged {
+                    address,
+                    slot,
+                    previous,
+                } => {
+                    let key = (address, slot);
+                    if previous.is_zero() {
+                        self.transient_storage.remove(&key);
+                    } else {
+                        self.transient_storage.insert(key, previous);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Makes the changes of the transaction final, deleting the accounts that
+    /// destructed, and hands them to the backend. The logs stay readable until
+    /// the next transaction starts.
+    pub fn commit(&mut self) {
+        self.journal.clear();
+        for address in self.selfdestructed.drain() {
+            self.db.remove_account(&address);
+        }
+        self.created.clear();
+        self.original_storage.clear();
+        self.warm_accounts.clear();
+        self.warm_slots.clear();
+        self.refund = 0;
+        self.transient_storage.clear();
+        self.db.commit();
+    }
+
+    /// Drops what the previous transaction left behind.
+    pub(crate) fn begin_transaction(&mut self) {
+        self.journal.clear();
+        self.logs.clear();
+        self.selfdestructed.clear();
+        self.created.clear();
+        self.original_storage.clear();
+        self.warm_accounts.clear();
+        self.warm_slots.clear();
+        self.refund = 0;
+        self.transient_storage.clear();
+    }
+
+    /// Returns the account at `address`, recording its creation first if it
+    /// does not exist yet.
+    fn touch(&mut self, address: &TransparentU256) -> AccountInfo {
+        if self.db.get_account_info(address).is_none() {
+            self.journal.push(JournalEntry::AccountCreated {
+                address: address.clone(),
+            });
+            self.db.insert_account(address.clone(), Account::default());
+        }
+        self.db
+            .get_account_info(address)
+            .expect("account was just inserted")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::InMemoryDb;
+
+    fn address(n: u64) -> TransparentU256 {
+        n.into()
+    }
+
+    fn funded(balance: u64) -> JournaledState<InMemoryDb> {
+        let mut db = InMemoryDb::new();
+        db.insert_account(
+            address(1),
+            Account {
+                balance,
+                ..Account::default()
+            },
+        );
+        JournaledState::new(db)
+    }
+
+    fn balance(state: &JournaledState<InMemoryDb>, n: u64) -> Option<u64> {
+        state.get_account_info(&address(n)).map(|a| a.balance)
+    }
+
+    #[test]
+    fn nested_checkpoints_revert_independently() {
+        let mut state = funded(100);
+        let outer = state.checkpoint();
+        state.set_balance(&address(1), 90);
+        state.set_storage(&address(1), 1.into(), 7.into());
+
+        let inner = state.checkpoint();
+        state.set_balance(&address(1), 80);
+        state.set_storage(&address(1), 1.into(), 8.into());
+        state.revert_to(inner);
+        assert_eq!(balance(&state, 1), Some(90));
+        assert_eq!(state.get_storage(&address(1), &1.into()), 7.into());
+
+        state.revert_to(outer);
+        assert_eq!(balance(&state, 1), Some(100));
+        assert_eq!(state.get_storage(&address(1), &1.into()), U256::zero());
+    }
+
+    #[test]
+    fn revert_removes_created_accounts() {
+        let mut state = funded(100);
+        let checkpoint = state.checkpoint();
+        state.set_nonce(&address(2), 1);
+        state.set_code(&address(2), vec![0x00]);
+        state.mark_created(&address(2));
+        assert!(state.is_created(&address(2)));
+
+        state.revert_to(checkpoint);
+        assert!(state.get_account_info(&address(2)).is_none());
+        assert!(!state.is_created(&address(2)));
+    }
+
+    #[test]
+    fn revert_restores_transient_storage() {
+        let mut state = funded(100);
+        state.set_transient_storage(&address(1), 1.into(), 5.into());
+        let checkpoint = state.checkpoint();
+        state.set_transient_storage(&address(1), 1.into(), U256::zero());
+        state.set_transient_storage(&address(1), 2.into(), 6.into());
+
+        state.revert_to(checkpoint);
+        assert_eq!(
+            state.get_transient_storage(&address(1), &1.into()),
+            5.into()
+        );
+        assert_eq!(
+            state.get_transient_storage(&address(1), &2.into()),
+            U256::zero()
+        );
+    }
+
+    #[test]
+    fn revert_restores_refund_and_access_sets() {
+        let mut state = funded(100);
+        state.add_refund(4800);
+        assert!(state.access_account(&address(1)));
+        let checkpoint = state.checkpoint();
+        state.add_refund(-2800);
+        assert!(!state.access_account(&address(1)));
+        assert!(state.access_account(&address(2)));
+        assert!(state.access_storage(&address(2), 1.into()));
+        assert!(state.selfdestruct(&address(1)));
+
+        state.revert_to(checkpoint);
+        assert_eq!(state.refund(), 4800);
+        assert!(!state.access_account(&address(1)));
+        assert!(state.access_account(&address(2)));
+        assert!(state.access_storage(&address(2), 1.into()));
+        assert!(state.selfdestructed().is_empty());
+    }
+
+    #[test]
+    fn revert_drops_logs() {
+        let mut state = funded(100);
+        let checkpoint = state.checkpoint();
+        state.log(EVMLog {
+            address: address(1),
+            data: vec![1],
+            topics: Vec::new(),
+        });
+        state.revert_to(checkpoint);
+        assert!(state.logs().is_empty());
+    }
+
+    #[test]
+    fn commit_keeps_changes_and_resets_the_transaction() {
+        let mut state = funded(100);
+        state.set_balance(&address(1), 50);
+        state.set_storage(&address(1), 1.into(), 7.into());
+        state.set_transient_storage(&address(1), 1.into(), 5.into());
+        state.set_balance(&address(3), 1);
+        state.selfdestruct(&address(3));
+        state.mark_created(&address(3));
+        state.access_account(&address(1));
+        state.access_storage(&address(1), 1.into());
+        state.add_refund(4800);
+        state.log(EVMLog {
+            address: address(1),
+            data: Vec::new(),
+            topics: Vec::new(),
+        });
+
+        state.commit();
+        assert_eq!(balance(&state, 1), Some(50));
+        assert_eq!(state.get_storage(&address(1), &1.into()), 7.into());
+        assert_eq!(state.original_storage(&address(1), &1.into()), 7.into());
+        assert!(state.get_account_info(&address(3)).is_none());
+        assert!(state.selfdestructed().is_empty());
+        assert!(!state.is_created(&address(3)));
+        assert_eq!(
+            state.get_transient_storage(&address(1), &1.into()),
+            U256::zero()
+        );
+        assert!(state.access_account(&address(1)));
+        assert!(state.access_storage(&address(1), 1.into()));
+        assert_eq!(state.refund(), 0);
+        assert_eq!(state.logs().len(), 1);
+
+        // nothing before the commit can be undone any more
+        state.revert_to(Checkpoint { journal_len: 0 });
+        assert_eq!(balance(&state, 1), Some(50));
+    }
+}
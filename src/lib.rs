@@ -1 +1,12 @@
+pub mod address;
+pub mod error;
+pub mod gas;
+pub mod i256;
+pub mod interpreter;
+pub mod journal;
+pub mod memory;
 pub mod op_code;
+pub mod precompile;
+pub mod spec;
+pub mod state;
+pub mod types;
@@ -0,0 +1,72 @@
+use primitive_types::U256;
+
+/// Byte-addressed scratch memory of a call frame. It starts empty and only
+/// ever grows, one 32-byte word at a time.
+#[derive(Debug, Clone, Default)]
+pub struct Memory {
+    data: Vec<u8>,
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Current size in bytes, always a multiple of 32.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn words(&self) -> usize {
+        self.data.len() / 32
+    }
+
+    pub fn as_slice(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Grows memory so that `offset..offset + len` is addressable. An empty
+    /// range never grows it, whatever the offset.
+    pub fn resize(&mut self, offset: usize, len: usize) {
+        if len == 0 {
+            return;
+        }
+        let size = (offset + len).div_ceil(32) * 32;
+        if size > self.data.len() {
+            self.data.resize(size, 0);
+        }
+    }
+
+    /// Panics if the range was not `resize`d first.
+    pub fn get_slice(&self, offset: usize, len: usize) -> &[u8] {
+        &self.data[offset..offset + len]
+    }
+
+    pub fn set(&mut self, offset: usize, value: &[u8]) {
+        self.data[offset..offset + value.len()].copy_from_slice(value);
+    }
+
+    /// Copies `len` bytes of `data` from `data_offset` to `offset`. Bytes
+    /// past the end of `data` are copied as zero.
+    pub fn set_data(&mut self, offset: usize, data_offset: U256, len: usize, data: &[u8]) {
+        let dest = &mut self.data[offset..offset + len];
+        if data_offset >= U256::from(data.len()) {
+            dest.fill(0);
+            return;
+        }
+        let data_offset = data_offset.as_usize();
+        let end = data.len().min(data_offset + len);
+        let copied = end - data_offset;
+        dest[..copied].copy_from_slice(&data[data_offset..end]);
+        dest[copied..].fill(0);
+    }
+
+    /// Copies `len` bytes from `src` to `dest`; the ranges may overlap.
+    pub fn copy_within(&mut self, src: usize, dest: usize, len: usize) {
+        self.data.copy_within(src..src + len, dest);
+    }
+}
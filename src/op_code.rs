@@ -8,9 +8,15 @@ pub const SUB: u8 = 0x03;
 pub const DIV: u8 = 0x04;
 pub const SDIV: u8 = 0x05;
 pub const MOD: u8 = 0x06;
+pub const SMOD: u8 = 0x07;
+pub const ADDMOD: u8 = 0x08;
+pub const MULMOD: u8 = 0x09;
 pub const EXP: u8 = 0x0A;
+pub const SIGNEXTEND: u8 = 0x0B;
 pub const LT: u8 = 0x10;
 pub const GT: u8 = 0x11;
+pub const SLT: u8 = 0x12;
+pub const SGT: u8 = 0x13;
 pub const EQ: u8 = 0x14;
 pub const ISZERO: u8 = 0x15;
 pub const AND: u8 = 0x16;
@@ -19,6 +25,7 @@ pub const XOR: u8 = 0x18;
 pub const NOT: u8 = 0x19;
 pub const SHL: u8 = 0x1B;
 pub const SHR: u8 = 0x1C;
+pub const SAR: u8 = 0x1D;
 pub const BYTE: u8 = 0x1A;
 pub const MSTORE: u8 = 0x52;
 pub const MSTORE8: u8 = 0x53;
@@ -26,6 +33,9 @@ pub const MLOAD: u8 = 0x51;
 pub const MSIZE: u8 = 0x59;
 pub const SSTORE: u8 = 0x55;
 pub const SLOAD: u8 = 0x54;
+pub const TLOAD: u8 = 0x5C;
+pub const TSTORE: u8 = 0x5D;
+pub const MCOPY: u8 = 0x5E;
 pub const STOP: u8 = 0x00;
 pub const JUMPDEST: u8 = 0x5b;
 pub const JUMP: u8 = 0x56;
@@ -35,11 +45,14 @@ pub const BLOCKHASH: u8 = 0x40;
 pub const COINBASE: u8 = 0x41;
 pub const TIMESTAMP: u8 = 0x42;
 pub const NUMBER: u8 = 0x43;
+// DIFFICULTY before the Merge
 pub const PREVRANDAO: u8 = 0x44;
 pub const GASLIMIT: u8 = 0x45;
 pub const CHAINID: u8 = 0x46;
 pub const SELFBALANCE: u8 = 0x47;
 pub const BASEFEE: u8 = 0x48;
+pub const BLOBHASH: u8 = 0x49;
+pub const BLOBBASEFEE: u8 = 0x4A;
 pub const DUP1: u8 = 0x80;
 pub const DUP16: u8 = 0x8F;
 pub const SWAP1: u8 = 0x90;
@@ -53,6 +66,12 @@ pub const ADDRESS: u8 = 0x30;
 pub const ORIGIN: u8 = 0x32;
 pub const CALLER: u8 = 0x33;
 pub const CALLVALUE: u8 = 0x34;
+pub const CALLDATALOAD: u8 = 0x35;
+pub const CALLDATASIZE: u8 = 0x36;
+pub const CALLDATACOPY: u8 = 0x37;
+pub const CODESIZE: u8 = 0x38;
+pub const CODECOPY: u8 = 0x39;
+pub const GASPRICE: u8 = 0x3A;
 pub const LOG0: u8 = 0xA0;
 pub const LOG1: u8 = 0xA1;
 pub const LOG2: u8 = 0xA2;
@@ -63,7 +82,11 @@ pub const RETURNDATASIZE: u8 = 0x3D;
 pub const RETURNDATACOPY: u8 = 0x3E;
 pub const REVERT: u8 = 0xFD;
 pub const INVALID: u8 = 0xFE;
+pub const CREATE: u8 = 0xF0;
 pub const CALL: u8 = 0xF1;
+pub const CALLCODE: u8 = 0xF2;
+pub const DELEGATECALL: u8 = 0xF4;
+pub const CREATE2: u8 = 0xF5;
 pub const STATICCALL: u8 = 0xFA;
 pub const SELFDESTRUCT: u8 = 0xFF;
 pub const GAS: u8 = 0x5A;
@@ -0,0 +1,684 @@
+use std::collections::HashMap;
+
+use c_kzg::{Bytes32, Bytes48};
+use k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use primitive_types::U256;
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+use sha3::Keccak256;
+use substrate_bn::{AffineG1, AffineG2, Fq, Fq2, Fr, Group, Gt, G1, G2};
+
+use crate::{error::PrecompileError, gas::num_words, spec::SpecId, types::TransparentU256};
+
+pub const ECRECOVER_COST: u64 = 3000;
+pub const SHA256_COST: u64 = 60;
+pub const SHA256_WORD: u64 = 12;
+pub const RIPEMD160_COST: u64 = 600;
+pub const RIPEMD160_WORD: u64 = 120;
+pub const IDENTITY_COST: u64 = 15;
+pub const IDENTITY_WORD: u64 = 3;
+// EIP-2565 floor on the price of MODEXP
+pub const MODEXP_MIN_COST: u64 = 200;
+// EIP-1108 cut the BN254 prices in Istanbul, the second of each pair is the
+// Byzantium price
+pub const BN254_ADD_COST: (u64, u64) = (150, 500);
+pub const BN254_MUL_COST: (u64, u64) = (6000, 40000);
+pub const BN254_PAIRING_COST: (u64, u64) = (45000, 100000);
+pub const BN254_PAIRING_PAIR: (u64, u64) = (34000, 80000);
+pub const BLAKE2F_ROUND: u64 = 1;
+pub const POINT_EVALUATION_COST: u64 = 50000;
+
+// EIP-4844 point evaluation
+pub const VERSIONED_HASH_VERSION_KZG: u8 = 0x01;
+const FIELD_ELEMENTS_PER_BLOB: u64 = 4096;
+const BLS_MODULUS: &str = "73eda753299d7d483339d80809a1d80553bda402fffe5bfeffffffff00000001";
+
+/// What a precompile returns when it succeeds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrecompileOutput {
+    pub gas_used: u64,
+    pub output: Vec<u8>,
+}
+
+pub type PrecompileResult = Result<PrecompileOutput, PrecompileError>;
+
+/// A contract implemented natively. It is handed the call data, the gas the
+/// call was given and the fork it runs under, and must not report using
+/// more gas than it was given.
+pub trait Precompile {
+    fn run(&self, input: &[u8], gas_limit: u64, spec: SpecId) -> PrecompileResult;
+}
+
+impl<F> Precompile for F
+where
+    F: Fn(&[u8], u64, SpecId) -> PrecompileResult,
+{
+    fn run(&self, input: &[u8], gas_limit: u64, spec: SpecId) -> PrecompileResult {
+        self(input, gas_limit, spec)
+    }
+}
+
+/// Precompiles by address, each with the fork that activated it. The
+/// default registry holds the mainnet ones; embedders can add their own.
+pub struct Precompiles {
+    precompiles: HashMap<TransparentU256, (SpecId, Box<dyn Precompile>)>,
+}
+
+impl Precompiles {
+    /// A registry without any precompile.
+    pub fn empty() -> Self {
+        Self {
+            precompiles: HashMap::new(),
+        }
+    }
+
+    /// Installs `precompile` at `address` from fork `since` on, replacing
+    /// whatever was there.
+    pub fn register(
+        &mut self,
+        address: TransparentU256,
+        since: SpecId,
+        precompile: impl Precompile + 'static,
+    ) {
+        self.precompiles
+            .insert(address, (since, Box::new(precompile)));
+    }
+
+    pub fn get(&self, address: &TransparentU256, spec: SpecId) -> Option<&dyn Precompile> {
+        match self.precompiles.get(address) {
+            Some((since, precompile)) if spec.is_enabled(*since) => Some(precompile.as_ref()),
+            _ => None,
+        }
+    }
+
+    /// Addresses of the precompiles active in `spec`.
+    pub fn addresses(&self, spec: SpecId) -> impl Iterator<Item = &TransparentU256> {
+        self.precompiles
+            .iter()
+            .filter(move |(_, (since, _))| spec.is_enabled(*since))
+            .map(|(address, _)| address)
+    }
+}
+
+impl Default for Precompiles {
+    fn default() -> Self {
+        let mut precompiles = Self::empty();
+        precompiles.register(0x01.into(), SpecId::Frontier, ecrecover);
+        precompiles.register(0x02.into(), SpecId::Frontier, sha256);
+        precompiles.register(0x03.into(), SpecId::Frontier, ripemd160);
+        precompiles.register(0x04.into(), SpecId::Frontier, identity);
+        precompiles.register(0x05.into(), SpecId::Byzantium, modexp);
+        precompiles.register(0x06.into(), SpecId::Byzantium, bn254_add);
+        precompiles.register(0x07.into(), SpecId::Byzantium, bn254_mul);
+        precompiles.register(0x08.into(), SpecId::Byzantium, bn254_pairing);
+        precompiles.register(0x09.into(), SpecId::Istanbul, blake2f);
+        precompiles.register(0x0a.into(), SpecId::Cancun, point_evaluation);
+        precompiles
+    }
+}
+
+/// Charges `cost`, failing if the call was not given that much.
+fn charge(cost: u64, gas_limit: u64) -> Result<u64, PrecompileError> {
+    if cost > gas_limit {
+        return Err(PrecompileError::OutOfGas);
+    }
+    Ok(cost)
+}
+
+/// `len` bytes of `input` from `offset` on, reading zero past its end.
+fn padded(input: &[u8], offset: usize, len: usize) -> Vec<u8> {
+    let mut data = vec![0u8; len];
+    if offset < input.len() {
+        let end = input.len().min(offset.saturating_add(len));
+        data[..end - offset].copy_from_slice(&input[offset..end]);
+    }
+    data
+}
+
+/// Recovers the address that signed a hash. A signature that does not
+/// recover is no error, the output is just empty.
+pub fn ecrecover(input: &[u8], gas_limit: u64, _spec: SpecId) -> PrecompileResult {
+    let gas_used = charge(ECRECOVER_COST, gas_limit)?;
+    let input = padded(input, 0, 128);
+    let output = recover_address(&input).map_or(Vec::new(), |address| address.to_vec());
+    Ok(PrecompileOutput { gas_used, output })
+}
+
+/// `input` is the hash, v, r and s, one word each.
+fn recover_address(input: &[u8]) -> Option<[u8; 32]> {
+    // v is a whole word holding 27 or 28
+    if input[32..63].iter().any(|b| *b != 0) || !matches!(input[63], 27 | 28) {
+        return None;
+    }
+    let mut signature = Signature::from_slice(&input[64..128]).ok()?;
+    let mut recovery_id = input[63] - 27;
+    // unlike transactions, the precompile accepts a high s
+    if let Some(normalized) = signature.normalize_s() {
+        signature = normalized;
+        recovery_id ^= 1;
+    }
+    let recovery_id = RecoveryId::from_byte(recovery_id)?;
+    let key = VerifyingKey::recover_from_prehash(&input[..32], &signature, recovery_id).ok()?;
+    let point = key.to_encoded_point(false);
+    let hash = Keccak256::digest(&point.as_bytes()[1..]);
+    let mut address = [0u8; 32];
+    address[12..].copy_from_slice(&hash[12..]);
+    Some(address)
+}
+
+pub fn sha256(input: &[u8], gas_limit: u64, _spec: SpecId) -> PrecompileResult {
+    let cost = SHA256_COST + SHA256_WORD * num_words(input.len());
+    let gas_used = charge(cost, gas_limit)?;
+    let output = Sha256::digest(input).to_vec();
+    Ok(PrecompileOutput { gas_used, output })
+}
+
+/// The 20-byte hash comes back left-padded to a word.
+pub fn ripemd160(input: &[u8], gas_limit: u64, _spec: SpecId) -> PrecompileResult {
+    let cost = RIPEMD160_COST + RIPEMD160_WORD * num_words(input.len());
+    let gas_used = charge(cost, gas_limit)?;
+    let mut output = vec![0u8; 32];
+    output[12..].copy_from_slice(&Ripemd160::digest(input));
+    Ok(PrecompileOutput { gas_used, output })
+}
+
+pub fn identity(input: &[u8], gas_limit: u64, _spec: SpecId) -> PrecompileResult {
+    let cost = IDENTITY_COST + IDENTITY_WORD * num_words(input.len());
+    let gas_used = charge(cost, gas_limit)?;
+    Ok(PrecompileOutput {
+        gas_used,
+        output: input.to_vec(),
+    })
+}
+
+/// `base ** exp % modulus` on numbers of any size (EIP-198). The input
+/// starts with the three lengths, one word each, followed by the numbers.
+pub fn modexp(input: &[u8], gas_limit: u64, spec: SpecId) -> PrecompileResult {
+    let base_len = U256::from_big_endian(&padded(input, 0, 32));
+    let exp_len = U256::from_big_endian(&padded(input, 32, 32));
+    let mod_len = U256::from_big_endian(&padded(input, 64, 32));
+    let data = input.get(96..).unwrap_or_default();
+
+    // only the first word of the exponent is priced
+    let exp_head = if base_len < U256::from(data.len()) {
+        let head_len = exp_len.min(32.into()).as_usize();
+        U256::from_big_endian(&padded(data, base_len.as_usize(), head_len))
+    } else {
+        U256::zero()
+    };
+    let cost = modexp_cost(spec, base_len, exp_len, mod_len, exp_head);
+    if cost > U256::from(gas_limit) {
+        return Err(PrecompileError::OutOfGas);
+    }
+    let gas_used = cost.as_u64();
+    if mod_len.is_zero() {
+        return Ok(PrecompileOutput {
+            gas_used,
+            output: Vec::new(),
+        });
+    }
+
+    // paying for them keeps the lengths small enough from here on
+    let (base_len, exp_len, mod_len) =
+        (base_len.as_usize(), exp_len.as_usize(), mod_len.as_usize());
+    let base = padded(data, 0, base_len);
+    let exponent = padded(data, base_len, exp_len);
+    let modulus = padded(data, base_len + exp_len, mod_len);
+    let result = aurora_engine_modexp::modexp(&base, &exponent, &modulus);
+    let mut output = vec![0u8; mod_len];
+    output[mod_len - result.len()..].copy_from_slice(&result);
+    Ok(PrecompileOutput { gas_used, output })
+}
+
+/// Price of MODEXP under EIP-2565 since Berlin, EIP-198 before.
+fn modexp_cost(spec: SpecId, base_len: U256, exp_len: U256, mod_len: U256, exp_head: U256) -> U256 {
+    let head_bits = U256::from(exp_head.bits().saturating_sub(1));
+    let iterations = if exp_len <= U256::from(32) {
+        head_bits
+    } else {
+        (exp_len - 32)
+            .saturating_mul(8.into())
+            .saturating_add(head_bits)
+    }
+    .max(U256::one());
+
+    let max_len = base_len.max(mod_len);
+    if spec.is_enabled(SpecId::Berlin) {
+        let words = max_len.saturating_add(7.into()) / 8;
+        let complexity = words.saturating_mul(words);
+        (complexity.saturating_mul(iterations) / 3).max(MODEXP_MIN_COST.into())
+    } else {
+        let square = max_len.saturating_mul(max_len);
+        let complexity = if max_len <= U256::from(64) {
+            square
+        } else if max_len <= U256::from(1024) {
+            square / 4 + max_len * 96 - 3072
+        } else {
+            (square / 16)
+                .saturating_add(max_len.saturating_mul(480.into()))
+                .saturating_sub(199680.into())
+        };
+        complexity.saturating_mul(iterations) / 20
+    }
+}
+
+/// Picks the Istanbul or the Byzantium price.
+fn bn254_cost(spec: SpecId, (istanbul, byzantium): (u64, u64)) -> u64 {
+    if spec.is_enabled(SpecId::Istanbul) {
+        istanbul
+    } else {
+        byzantium
+    }
+}
+
+fn read_fq(input: &[u8]) -> Result<Fq, PrecompileError> {
+    Fq::from_slice(input).map_err(|_| PrecompileError::InvalidPoint)
+}
+
+/// Reads a G1 point from two words, with (0, 0) standing for infinity.
+fn read_g1(input: &[u8]) -> Result<G1, PrecompileError> {
+    let x = read_fq(&input[..32])?;
+    let y = read_fq(&input[32..64])?;
+    if x.is_zero() && y.is_zero() {
+        return Ok(G1::zero());
+    }
+    AffineG1::new(x, y)
+        .map(Into::into)
+        .map_err(|_| PrecompileError::InvalidPoint)
+}
+
+/// Reads a G2 point from four words. Each coordinate is encoded with its
+/// imaginary part first.
+fn read_g2(input: &[u8]) -> Result<G2, PrecompileError> {
+    let x = Fq2::new(read_fq(&input[32..64])?, read_fq(&input[..32])?);
+    let y = Fq2::new(read_fq(&input[96..128])?, read_fq(&input[64..96])?);
+    if x.is_zero() && y.is_zero() {
+        return Ok(G2::zero());
+    }
+    AffineG2::new(x, y)
+        .map(Into::into)
+        .map_err(|_| PrecompileError::InvalidPoint)
+}
+
+fn write_g1(point: G1) -> Vec<u8> {
+    let mut output = vec![0u8; 64];
+    // infinity has no affine form and stays (0, 0)
+    if let Some(point) = AffineG1::from_jacobian(point) {
+        point
+            .x()
+            .to_big_endian(&mut output[..32])
+            .expect("a word holds a coordinate");
+        point
+            .y()
+            .to_big_endian(&mut output[32..])
+            .expect("a word holds a coordinate");
+    }
+    output
+}
+
+/// Adds two BN254 points (EIP-196).
+pub fn bn254_add(input: &[u8], gas_limit: u64, spec: SpecId) -> PrecompileResult {
+    let gas_used = charge(bn254_cost(spec, BN254_ADD_COST), gas_limit)?;
+    let input = padded(input, 0, 128);
+    let sum = read_g1(&input[..64])? + read_g1(&input[64..])?;
+    Ok(PrecompileOutput {
+        gas_used,
+        output: write_g1(sum),
+    })
+}
+
+/// Multiplies a BN254 point by a scalar (EIP-196).
+pub fn bn254_mul(input: &[u8], gas_limit: u64, spec: SpecId) -> PrecompileResult {
+    let gas_used = charge(bn254_cost(spec, BN254_MUL_COST), gas_limit)?;
+    let input = padded(input, 0, 96);
+    let point = read_g1(&input[..64])?;
+    let scalar = Fr::from_slice(&input[64..]).expect("a word is a scalar");
+    Ok(PrecompileOutput {
+        gas_used,
+        output: write_g1(point * scalar),
+    })
+}
+
+/// Checks that the product of the pairings of the given (G1, G2) pairs is
+/// one (EIP-197). Returns 1 or 0 as a word.
+pub fn bn254_pairing(input: &[u8], gas_limit: u64, spec: SpecId) -> PrecompileResult {
+    let pairs = (input.len() / 192) as u64;
+    let cost = bn254_cost(spec, BN254_PAIRING_COST)
+        .saturating_add(bn254_cost(spec, BN254_PAIRING_PAIR).saturating_mul(pairs));
+    let gas_used = charge(cost, gas_limit)?;
+    if !input.len().is_multiple_of(192) {
+        return Err(PrecompileError::InvalidInputLength);
+    }
+
+    let mut points = Vec::with_capacity(pairs as usize);
+    for pair in input.chunks(192) {
+        points.push((read_g1(&pair[..64])?, read_g2(&pair[64..])?));
+    }
+    let mut output = vec![0u8; 32];
+    // no pairs at all is a product of one
+    if substrate_bn::pairing_batch(&points) == Gt::one() {
+        output[31] = 1;
+    }
+    Ok(PrecompileOutput { gas_used, output })
+}
+
+const BLAKE2F_INPUT_LEN: usize = 213;
+
+const BLAKE2B_IV: [u64; 8] = [
+    0x6a09e667f3bcc908,
+    0xbb67ae8584caa73b,
+    0x3c6ef372fe94f82b,
+    0xa54ff53a5f1d36f1,
+    0x510e527fade682d1,
+    0x9b05688c2b3e6c1f,
+    0x1f83d9abfb41bd6b,
+    0x5be0cd19137e2179,
+];
+
+const BLAKE2B_SIGMA: [[usize; 16]; 10] = [
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [14, 10, 4, 8, 9, 15, 13, 6, 1, 12, 0, 2, 11, 7, 5, 3],
+    [11, 8, 12, 0, 5, 2, 15, 13, 10, 14, 3, 6, 7, 1, 9, 4],
+    [7, 9, 3, 1, 13, 12, 11, 14, 2, 6, 5, 10, 4, 0, 15, 8],
+    [9, 0, 5, 7, 2, 4, 10, 15, 14, 1, 11, 12, 6, 8, 3, 13],
+    [2, 12, 6, 10, 0, 11, 8, 3, 4, 13, 7, 5, 15, 14, 1, 9],
+    [12, 5, 1, 15, 14, 13, 4, 10, 0, 7, 6, 3, 9, 2, 8, 11],
+    [13, 11, 7, 14, 12, 1, 3, 9, 5, 0, 15, 4, 8, 6, 2, 10],
+    [6, 15, 14, 9, 11, 3, 0, 8, 12, 2, 13, 7, 1, 4, 10, 5],
+    [10, 2, 8, 4, 7, 6, 1, 5, 15, 11, 9, 14, 3, 12, 13, 0],
+];
+
+/// Runs the BLAKE2b compression function F for a caller-chosen number of
+/// rounds (EIP-152). The input is the rounds, the state h, the message
+/// block m, the offset counter t and the final block flag, with every
+/// word but the rounds little-endian.
+pub fn blake2f(input: &[u8], gas_limit: u64, _spec: SpecId) -> PrecompileResult {
+    if input.len() != BLAKE2F_INPUT_LEN {
+        return Err(PrecompileError::InvalidInputLength);
+    }
+    let rounds = u32::from_be_bytes(input[..4].try_into().expect("4 bytes"));
+    let gas_used = charge(BLAKE2F_ROUND * rounds as u64, gas_limit)?;
+    let final_block = match input[212] {
+        0 => false,
+        1 => true,
+        _ => return Err(PrecompileError::InvalidFinalFlag),
+    };
+
+    let word =
+        |offset: usize| u64::from_le_bytes(input[offset..offset + 8].try_into().expect("8 bytes"));
+    let mut h: [u64; 8] = std::array::from_fn(|i| word(4 + 8 * i));
+    let m: [u64; 16] = std::array::from_fn(|i| word(68 + 8 * i));
+    let t = [word(196), word(204)];
+    blake2b_compress(rounds as usize, &mut h, &m, &t, final_block);
+
+    let output = h.iter().flat_map(|word| word.to_le_bytes()).collect();
+    Ok(PrecompileOutput { gas_used, output })
+}
+
+fn blake2b_compress(rounds: usize, h: &mut [u64; 8], m: &[u64; 16], t: &[u64; 2], last: bool) {
+    let mut v = [0u64; 16];
+    v[..8].copy_from_slice(h);
+    v[8..].copy_from_slice(&BLAKE2B_IV);
+    v[12] ^= t[0];
+    v[13] ^= t[1];
+    if last {
+        v[14] = !v[14];
+    }
+    for round in 0..rounds {
+        let s = &BLAKE2B_SIGMA[round % 10];
+        blake2b_mix(&mut v, [0, 4, 8, 12], m[s[0]], m[s[1]]);
+        blake2b_mix(&mut v, [1, 5, 9, 13], m[s[2]], m[s[3]]);
+        blake2b_mix(&mut v, [2, 6, 10, 14], m[s[4]], m[s[5]]);
+        blake2b_mix(&mut v, [3, 7, 11, 15], m[s[6]], m[s[7]]);
+        blake2b_mix(&mut v, [0, 5, 10, 15], m[s[8]], m[s[9]]);
+        blake2b_mix(&mut v, [1, 6, 11, 12], m[s[10]], m[s[11]]);
+        blake2b_mix(&mut v, [2, 7, 8, 13], m[s[12]], m[s[13]]);
+        blake2b_mix(&mut v, [3, 4, 9, 14], m[s[14]], m[s[15]]);
+    }
+    for i in 0..8 {
+        h[i] ^= v[i] ^ v[i + 8];
+    }
+}
+
+/// The mixing function G of RFC 7693.
+fn blake2b_mix(v: &mut [u64; 16], [a, b, c, d]: [usize; 4], x: u64, y: u64) {
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(x);
+    v[d] = (v[d] ^ v[a]).rotate_right(32);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(24);
+    v[a] = v[a].wrapping_add(v[b]).wrapping_add(y);
+    v[d] = (v[d] ^ v[a]).rotate_right(16);
+    v[c] = v[c].wrapping_add(v[d]);
+    v[b] = (v[b] ^ v[c]).rotate_right(63);
+}
+
+/// Verifies that the blob committed to evaluates to y at z (EIP-4844). The
+/// input is the versioned hash, z, y, the commitment and the proof.
+pub fn point_evaluation(input: &[u8], gas_limit: u64, _spec: SpecId) -> PrecompileResult {
+    let gas_used = charge(POINT_EVALUATION_COST, gas_limit)?;
+    if input.len() != 192 {
+        return Err(PrecompileError::InvalidInputLength);
+    }
+    let commitment = &input[96..144];
+    let mut versioned_hash = Sha256::digest(commitment);
+    versioned_hash[0] = VERSIONED_HASH_VERSION_KZG;
+    if input[..32] != versioned_hash[..] {
+        return Err(PrecompileError::InvalidVersionedHash);
+    }
+
+    let bytes32 =
+        |offset: usize| Bytes32::new(input[offset..offset + 32].try_into().expect("32 bytes"));
+    let bytes48 =
+        |offset: usize| Bytes48::new(input[offset..offset + 48].try_into().expect("48 bytes"));
+    let verified = c_kzg::ethereum_kzg_settings(0)
+        .verify_kzg_proof(&bytes48(96), &bytes32(32), &bytes32(64), &bytes48(144))
+        .unwrap_or(false);
+    if !verified {
+        return Err(PrecompileError::InvalidProof);
+    }
+
+    let mut output = vec![0u8; 64];
+    U256::from(FIELD_ELEMENTS_PER_BLOB).to_big_endian(&mut output[..32]);
+    output[32..].copy_from_slice(&hex::decode(BLS_MODULUS).expect("valid hex"));
+    Ok(PrecompileOutput { gas_used, output })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GAS: u64 = 1_000_000;
+    const G1: &str = "0000000000000000000000000000000000000000000000000000000000000001\
+                      0000000000000000000000000000000000000000000000000000000000000002";
+    // 2 * G1, the sum of the generator with itself
+    const G1_DOUBLED: &str = "030644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd3\
+                              15ed738c0e0a7c92e7845f96b2ae9c0a68a6a449e3538fc7ff3ebf7a5a18a2c4";
+
+    fn bytes(hex: &str) -> Vec<u8> {
+        hex::decode(hex).unwrap()
+    }
+
+    fn word(value: u64) -> Vec<u8> {
+        let mut word = vec![0u8; 32];
+        U256::from(value).to_big_endian(&mut word);
+        word
+    }
+
+    #[test]
+    fn ecrecover_recovers_the_signer() {
+        let input = bytes(
+            "18c547e4f7b0f325ad1e56f57e26c745b09a3e503d86e00e5255ff7f715d3d1c\
+             000000000000000000000000000000000000000000000000000000000000001c\
+             73b1693892219d736caba55bdb67216e485557ea6b6af75f37096c9aa6a5a75f\
+             eeb940b1d03b21e36b0e47e79769f095fe2ab855bd91e3a38756b7d75a9c4549",
+        );
+        let result = ecrecover(&input, GAS, SpecId::Cancun).unwrap();
+        assert_eq!(result.gas_used, ECRECOVER_COST);
+        assert_eq!(
+            result.output,
+            bytes("000000000000000000000000a94f5374fce5edbc8e2a8697c15331677e6ebf0b")
+        );
+
+        // v must be 27 or 28, anything else recovers nothing
+        let mut bad_v = input;
+        bad_v[63] = 29;
+        let result = ecrecover(&bad_v, GAS, SpecId::Cancun).unwrap();
+        assert_eq!(result.gas_used, ECRECOVER_COST);
+        assert!(result.output.is_empty());
+    }
+
+    #[test]
+    fn hashes_and_identity_charge_per_word() {
+        let result = sha256(&[], GAS, SpecId::Cancun).unwrap();
+        assert_eq!(result.gas_used, SHA256_COST);
+        assert_eq!(
+            result.output,
+            bytes("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+        );
+
+        let result = ripemd160(&[], GAS, SpecId::Cancun).unwrap();
+        assert_eq!(result.gas_used, RIPEMD160_COST);
+        assert_eq!(
+            result.output,
+            bytes("0000000000000000000000009c1185a5c5e9fc54612808977ee8f548b2258d31")
+        );
+
+        let result = identity(&[7; 33], GAS, SpecId::Cancun).unwrap();
+        assert_eq!(result.gas_used, IDENTITY_COST + 2 * IDENTITY_WORD);
+        assert_eq!(result.output, vec![7; 33]);
+    }
+
+    #[test]
+    fn modexp_prices_follow_the_fork() {
+        // EIP-198 example: 3^(p - 1) mod p == 1 for the secp256k1 field prime
+        let mut input = [word(1), word(32), word(32)].concat();
+        input.extend(bytes(
+            "03\
+             fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2e\
+             fffffffffffffffffffffffffffffffffffffffffffffffffffffffefffffc2f",
+        ));
+        let berlin = modexp(&input, GAS, SpecId::Berlin).unwrap();
+        assert_eq!(berlin.gas_used, 1360);
+        assert_eq!(berlin.output, word(1));
+        let byzantium = modexp(&input, GAS, SpecId::Byzantium).unwrap();
+        assert_eq!(byzantium.gas_used, 13056);
+        assert_eq!(byzantium.output, word(1));
+
+        assert_eq!(
+            modexp(&input, 1359, SpecId::Berlin),
+            Err(PrecompileError::OutOfGas)
+        );
+    }
+
+    #[test]
+    fn bn254_add_and_mul() {
+        let result = bn254_add(&bytes(&format!("{G1}{G1}")), GAS, SpecId::Istanbul).unwrap();
+        assert_eq!(result.gas_used, BN254_ADD_COST.0);
+        assert_eq!(result.output, bytes(G1_DOUBLED));
+        let result = bn254_add(&bytes(&format!("{G1}{G1}")), GAS, SpecId::Byzantium).unwrap();
+        assert_eq!(result.gas_used, BN254_ADD_COST.1);
+
+        let mut input = bytes(G1);
+        input.extend(word(2));
+        let result = bn254_mul(&input, GAS, SpecId::Istanbul).unwrap();
+        assert_eq!(result.gas_used, BN254_MUL_COST.0);
+        assert_eq!(result.output, bytes(G1_DOUBLED));
+    }
+
+    #[test]
+    fn bn254_rejects_points_off_the_curve() {
+        // (1, 3) does not satisfy y^2 = x^3 + 3
+        let mut input = bytes(G1);
+        input.extend([word(1), word(3)].concat());
+        assert_eq!(
+            bn254_add(&input, GAS, SpecId::Istanbul),
+            Err(PrecompileError::InvalidPoint)
+        );
+    }
+
+    #[test]
+    fn bn254_empty_pairing_holds() {
+        let result = bn254_pairing(&[], GAS, SpecId::Istanbul).unwrap();
+        assert_eq!(result.gas_used, BN254_PAIRING_COST.0);
+        assert_eq!(result.output, word(1));
+
+        assert_eq!(
+            bn254_pairing(&[0; 10], GAS, SpecId::Istanbul),
+            Err(PrecompileError::InvalidInputLength)
+        );
+        assert_eq!(
+            bn254_pairing(&[], BN254_PAIRING_COST.0 - 1, SpecId::Istanbul),
+            Err(PrecompileError::OutOfGas)
+        );
+    }
+
+    fn blake2f_input() -> Vec<u8> {
+        // EIP-152 test vector 5: 12 rounds of compressing "abc"
+        let mut input = bytes(
+            "0000000c\
+             48c9bdf267e6096a3ba7ca8485ae67bb2bf894fe72f36e3cf1361d5f3af54fa5\
+             d182e6ad7f520e511f6c3e2b8c68059b6bbd41fbabd9831f79217e1319cde05b\
+             616263",
+        );
+        input.resize(4 + 64 + 128, 0);
+        input.extend(bytes("0300000000000000000000000000000001"));
+        input
+    }
+
+    #[test]
+    fn blake2f_compresses() {
+        let result = blake2f(&blake2f_input(), GAS, SpecId::Istanbul).unwrap();
+        assert_eq!(result.gas_used, 12 * BLAKE2F_ROUND);
+        assert_eq!(
+            result.output,
+            bytes(
+                "ba80a53f981c4d0d6a2797b69f12f6e94c212f14685ac4b74b12bb6fdbffa2d1\
+                 7d87c5392aab792dc252d5de4533cc9518d38aa8dbf1925ab92386edd4009923"
+            )
+        );
+    }
+
+    #[test]
+    fn blake2f_rejects_bad_input() {
+        let mut bad_flag = blake2f_input();
+        bad_flag[212] = 2;
+        assert_eq!(
+            blake2f(&bad_flag, GAS, SpecId::Istanbul),
+            Err(PrecompileError::InvalidFinalFlag)
+        );
+
+        let mut short = blake2f_input();
+        short.pop();
+        assert_eq!(
+            blake2f(&short, GAS, SpecId::Istanbul),
+            Err(PrecompileError::InvalidInputLength)
+        );
+    }
+
+    #[test]
+    fn point_evaluation_checks_the_versioned_hash() {
+        assert_eq!(
+            point_evaluation(&[0; 191], GAS, SpecId::Cancun),
+            Err(PrecompileError::InvalidInputLength)
+        );
+        assert_eq!(
+            point_evaluation(&[0; 192], GAS, SpecId::Cancun),
+            Err(PrecompileError::InvalidVersionedHash)
+        );
+        assert_eq!(
+            point_evaluation(&[0; 192], POINT_EVALUATION_COST - 1, SpecId::Cancun),
+            Err(PrecompileError::OutOfGas)
+        );
+    }
+
+    #[test]
+    fn registry_follows_the_fork() {
+        let precompiles = Precompiles::default();
+        assert!(precompiles.get(&0x04.into(), SpecId::Frontier).is_some());
+        assert!(precompiles.get(&0x05.into(), SpecId::Homestead).is_none());
+        assert!(precompiles.get(&0x05.into(), SpecId::Byzantium).is_some());
+        assert!(precompiles.get(&0x0a.into(), SpecId::Shanghai).is_none());
+        assert_eq!(precompiles.addresses(SpecId::Istanbul).count(), 9);
+        assert_eq!(precompiles.addresses(SpecId::Cancun).count(), 10);
+    }
+}
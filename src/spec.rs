@@ -0,0 +1,49 @@
+use crate::op_code::*;
+
+/// Mainnet hard forks in activation order. A fork keeps every rule of the
+/// ones before it unless it replaces it, so forks compare by age.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SpecId {
+    Frontier,
+    Homestead,
+    TangerineWhistle,
+    SpuriousDragon,
+    Byzantium,
+    Constantinople,
+    Petersburg,
+    Istanbul,
+    Berlin,
+    London,
+    Merge,
+    Shanghai,
+    #[default]
+    Cancun,
+    Prague,
+}
+
+impl SpecId {
+    /// Whether `fork` is active, i.e. this is `fork` or a later one.
+    pub fn is_enabled(self, fork: SpecId) -> bool {
+        self >= fork
+    }
+
+    /// Whether `op` is defined in this fork. Bytes that are no opcode at
+    /// all are left for the interpreter to reject.
+    pub fn has_opcode(self, op: u8) -> bool {
+        self.is_enabled(introduced_in(op))
+    }
+}
+
+/// Fork that added `op` to the instruction set.
+fn introduced_in(op: u8) -> SpecId {
+    match op {
+        DELEGATECALL => SpecId::Homestead,
+        REVERT | RETURNDATASIZE | RETURNDATACOPY | STATICCALL => SpecId::Byzantium,
+        SHL | SHR | SAR | CREATE2 | EXTCODEHASH => SpecId::Constantinople,
+        CHAINID | SELFBALANCE => SpecId::Istanbul,
+        BASEFEE => SpecId::London,
+        PUSH0 => SpecId::Shanghai,
+        TLOAD | TSTORE | MCOPY | BLOBHASH | BLOBBASEFEE => SpecId::Cancun,
+        _ => SpecId::Frontier,
+    }
+}
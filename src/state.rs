@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+
+use primitive_types::U256;
+use sha3::{Digest, Keccak256};
+
+use crate::types::TransparentU256;
+
+/// Hash of empty code, keccak256("").
+pub const KECCAK_EMPTY: U256 = U256([
+    0x7bfad8045d85a470,
+    0xe500b653ca82273b,
+    0x927e7db2dcc703c0,
+    0xc5d2460186f7233c,
+]);
+
+#[derive(Debug, Clone, Default)]
+pub struct Account {
+    pub balance: u64,
+    pub nonce: u64,
+    pub storage: HashMap<U256, U256>,
+    pub code: Vec<u8>,
+}
+
+impl Account {
+    pub fn code_hash(&self) -> U256 {
+        hash_code(&self.code)
+    }
+
+    pub fn info(&self) -> AccountInfo {
+        AccountInfo {
+            balance: self.balance,
+            nonce: self.nonce,
+            code_hash: self.code_hash(),
+        }
+    }
+}
+
+fn hash_code(code: &[u8]) -> U256 {
+    U256::from(&Keccak256::digest(code)[..])
+}
+
+/// What most reads need of an account, without its storage or code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountInfo {
+    pub balance: u64,
+    pub nonce: u64,
+    pub code_hash: U256,
+}
+
+impl AccountInfo {
+    pub fn has_code(&self) -> bool {
+        self.code_hash != KECCAK_EMPTY
+    }
+
+    /// Empty as defined by EIP-161: no balance, nonce or code.
+    pub fn is_empty(&self) -> bool {
+        self.balance == 0 && self.nonce == 0 && !self.has_code()
+    }
+}
+
+impl Default for AccountInfo {
+    fn default() -> Self {
+        Self {
+            balance: 0,
+            nonce: 0,
+            code_hash: KECCAK_EMPTY,
+        }
+    }
+}
+
+/// Backend holding the world state. The interpreter reads and writes all
+/// accounts through it, and every nested call frame shares the same one.
+///
+/// Accounts, their storage slots and their code are read separately and
+/// handed back owned, so a backend is free to load each of them lazily.
+/// Setters on an address without an account create an empty one first.
+pub trait StateDb {
+    fn get_account_info(&self, address: &TransparentU256) -> Option<AccountInfo>;
+
+    fn insert_account(&mut self, address: TransparentU256, account: Account);
+
+    fn remove_account(&mut self, address: &TransparentU256);
+
+    fn set_balance(&mut self, address: &TransparentU256, balance: u64);
+
+    fn set_nonce(&mut self, address: &TransparentU256, nonce: u64);
+
+    fn set_code(&mut self, address: &TransparentU256, code: Vec<u8>);
+
+    /// Slots that were never written read as zero.
+    fn get_storage(&self, address: &TransparentU256, slot: &U256) -> U256;
+
+    fn set_storage(&mut self, address: &TransparentU256, slot: U256, value: U256);
+
+    fn get_code_by_hash(&self, code_hash: &U256) -> Option<Vec<u8>>;
+
+    /// Called once the changes of a transaction are final, so backends that
+    /// buffer writes know when to persist them.
+    fn commit(&mut self);
+}
+
+/// `StateDb` kept entirely in memory.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryDb {
+    accounts: HashMap<TransparentU256, DbAccount>,
+    contracts: HashMap<U256, Vec<u8>>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct DbAccount {
+    info: AccountInfo,
+    storage: HashMap<U256, U256>,
+}
+
+impl InMemoryDb {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every account with its storage and code.
+    pub fn accounts(&self) -> HashMap<TransparentU256, Account> {
+        self.accounts
+            .iter()
+            .map(|(address, account)| {
+                let account = Account {
+                    balance: account.info.balance,
+                    nonce: account.info.nonce,
+                    storage: account.storage.clone(),
+                    code: self.code(&account.info.code_hash),
+                };
+                (address.clone(), account)
+            })
+            .collect()
+    }
+
+    pub fn storage(&self, address: &TransparentU256) -> Option<&HashMap<U256, U256>> {
+        self.accounts.get(address).map(|account| &account.storage)
+    }
+
+    fn code(&self, code_hash: &U256) -> Vec<u8> {
+        self.contracts.get(code_hash).cloned().unwrap_or_default()
+    }
+
+    fn account_mut(&mut self, address: &TransparentU256) -> &mut DbAccount {
+        self.accounts.entry(address.clone()).or_default()
+    }
+}
+
+impl StateDb for InMemoryDb {
+    fn get_account_info(&self, address: &TransparentU256) -> Option<AccountInfo> {
+        self.accounts.get(address).map(|account| account.info)
+    }
+
+    fn insert_account(&mut self, address: TransparentU256, account: Account) {
+        let info = account.info();
+        self.contracts.insert(info.code_hash, account.code);
+        let storage = account.storage;
+        self.accounts.insert(address, DbAccount { info, storage });
+    }
+
+    fn remove_account(&mut self, address: &TransparentU256) {
+        self.accounts.remove(address);
+    }
+
+    fn set_balance(&mut self, address: &TransparentU256, balance: u64) {
+        self.account_mut(address).info.balance = balance;
+    }
+
+    fn set_nonce(&mut self, address: &TransparentU256, nonce: u64) {
+        self.account_mut(address).info.nonce = nonce;
+    }
+
+    fn set_code(&mut self, address: &TransparentU256, code: Vec<u8>) {
+        let code_hash = hash_code(&code);
+        self.account_mut(address).info.code_hash = code_hash;
+        self.contracts.insert(code_hash, code);
+    }
+
+    fn get_storage(&self, address: &TransparentU256, slot: &U256) -> U256 {
+        self.accounts
+            .get(address)
+            .and_then(|a| a.storage.get(slot).copied())
+            .unwrap_or_default()
+    }
+
+    fn set_storage(&mut self, address: &TransparentU256, slot: U256, value: U256) {
+        let storage = &mut self.account_mut(address).storage;
+        if value.is_zero() {
+            storage.remove(&slot);
+        } else {
+            storage.insert(slot, value);
+        }
+    }
+
+    fn get_code_by_hash(&self, code_hash: &U256) -> Option<Vec<u8>> {
+        self.contracts.get(code_hash).cloned()
+    }
+
+    // writes already land in the maps above
+    fn commit(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_code_hash() {
+        assert_eq!(Account::default().code_hash(), KECCAK_EMPTY);
+        assert!(AccountInfo::default().is_empty());
+    }
+
+    #[test]
+    fn code_is_read_by_hash() {
+        let mut db = InMemoryDb::new();
+        let address: TransparentU256 = 1.into();
+        db.set_balance(&address, 5);
+        let info = db.get_account_info(&address).unwrap();
+        assert!(!info.has_code() && !info.is_empty());
+
+        db.set_code(&address, vec![0x60, 0x00]);
+        let info = db.get_account_info(&address).unwrap();
+        assert_eq!(info.balance, 5);
+        assert_eq!(db.get_code_by_hash(&info.code_hash), Some(vec![0x60, 0x00]));
+    }
+}
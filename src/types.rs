@@ -0,0 +1,181 @@
+use std::{
+    fmt::{Debug, Formatter},
+    num::NonZeroU32,
+    ops::{Deref, DerefMut},
+    str::FromStr,
+};
+
+use primitive_types::U256;
+
+use crate::{
+    error::EvmError,
+    gas::{
+        fake_exponential, BLOB_BASE_FEE_UPDATE_FRACTION, BLOB_BASE_FEE_UPDATE_FRACTION_PRAGUE,
+        MIN_BLOB_BASE_FEE,
+    },
+    spec::SpecId,
+};
+
+#[derive(Debug, Clone)]
+pub struct Block {
+    pub blockhash: U256,
+    pub coinbase: U256,
+    pub timestamp: u64,
+    pub number: u64,
+    pub prevrandao: U256,
+    // what PREVRANDAO's opcode returned as DIFFICULTY before the Merge
+    pub difficulty: U256,
+    pub gaslimit: NonZeroU32,
+    pub chainid: u8,
+    pub basefee: NonZeroU32,
+    // EIP-4844 blob gas above the target, accumulated over previous blocks
+    pub excess_blob_gas: u64,
+}
+
+impl Block {
+    /// Price of one unit of blob gas under `spec`, following EIP-4844.
+    pub fn blob_basefee(&self, spec: SpecId) -> U256 {
+        let update_fraction = if spec.is_enabled(SpecId::Prague) {
+            BLOB_BASE_FEE_UPDATE_FRACTION_PRAGUE
+        } else {
+            BLOB_BASE_FEE_UPDATE_FRACTION
+        };
+        fake_exponential(MIN_BLOB_BASE_FEE, self.excess_blob_gas, update_fraction)
+    }
+}
+
+impl Default for Block {
+    fn default() -> Self {
+        Block {
+            blockhash: U256::from_str(
+                "0x7527123fc877fe753b3122dc592671b4902ebf2b325dd2c7224a43c0cbeee3ca",
+            )
+            .unwrap(),
+            coinbase: U256::from_str("0x388C818CA8B9251b393131C08a736A67ccB19297").unwrap(),
+            timestamp: 1625900000,
+            number: 17871709,
+            prevrandao: U256::from_str(
+                "0xce124dee50136f3f93f19667fb4198c6b94eecbacfa300469e5280012757be94",
+            )
+            .unwrap(),
+            difficulty: U256::zero(),
+            gaslimit: NonZeroU32::new(30_000_000).unwrap(),
+            chainid: 1,
+            basefee: NonZeroU32::new(1).unwrap(),
+            excess_blob_gas: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Transaction {
+    pub nonce: u64,
+    pub gas_price: u64,
+    pub gas_limit: u64,
+    pub to: TransparentU256,
+    pub value: u64,
+    pub data: Vec<u8>,
+    pub caller: TransparentU256,
+    pub origin: TransparentU256,
+    pub this_addr: TransparentU256,
+    pub access_list: Vec<AccessListItem>,
+    // the code run is init code, and what it returns is deployed at the
+    // address derived from the caller and nonce
+    pub is_create: bool,
+    // EIP-4844 blobs carried by the transaction and the most it pays per
+    // unit of blob gas
+    pub blob_versioned_hashes: Vec<U256>,
+    pub max_fee_per_blob_gas: u64,
+    pub v: u64,
+    pub r: u64,
+    pub s: u64,
+}
+
+impl Default for Transaction {
+    fn default() -> Self {
+        Transaction {
+            nonce: 0,
+            gas_price: 1,
+            gas_limit: 21000,
+            to: U256::from("").into(),
+            value: 0,
+            data: Vec::new(),
+            caller: U256::from("0x9bbfed6889322e016e0a02ee459d306fc19545d8").into(),
+            origin: U256::from("0x1000000000000000000000000000000000000c42").into(),
+            this_addr: U256::from("0x1000000000000000000000000000000000000c42").into(),
+            access_list: Vec::new(),
+            is_create: false,
+            blob_versioned_hashes: Vec::new(),
+            max_fee_per_blob_gas: 0,
+            v: 0,
+            r: 0,
+            s: 0,
+        }
+    }
+}
+
+/// Entry of an EIP-2930 access list: an account and the slots of it the
+/// transaction declares it will touch.
+#[derive(Debug, Clone, Default)]
+pub struct AccessListItem {
+    pub address: TransparentU256,
+    pub storage_keys: Vec<U256>,
+}
+
+#[derive(Debug, Clone)]
+pub struct EVMLog {
+    pub address: TransparentU256,
+    pub data: Vec<u8>,
+    pub topics: Vec<TransparentU256>,
+}
+
+/// Outcome of running a piece of code to completion.
+#[derive(Debug, Clone)]
+pub struct ExecutionResult {
+    pub success: bool,
+    pub gas_used: u64,
+    pub return_data: Vec<u8>,
+    // why the code halted exceptionally, None if it stopped or reverted
+    pub halt_reason: Option<EvmError>,
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct TransparentU256(pub U256);
+
+impl Debug for TransparentU256 {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+        write!(f, "{:}", self.0)
+    }
+}
+
+impl Default for TransparentU256 {
+    fn default() -> Self {
+        TransparentU256(U256::zero())
+    }
+}
+
+impl Deref for TransparentU256 {
+    type Target = U256;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for TransparentU256 {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl From<u64> for TransparentU256 {
+    fn from(value: u64) -> Self {
+        TransparentU256(U256::from(value))
+    }
+}
+
+impl From<U256> for TransparentU256 {
+    fn from(value: U256) -> Self {
+        TransparentU256(value)
+    }
+}